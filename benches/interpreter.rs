@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+
+const ITERATIONS: u32 = 10;
+
+// Variable-heavy straight-line code: globals read and written from nested
+// blocks that declare and shadow their own locals.
+fn variables_source() -> String {
+    let mut source = String::from("var total = 0;\nvar step = 1;\nvar name = \"rlox\";\n");
+    for index in 0..2_000 {
+        source.push_str(&format!(
+            "{{\n    var local_{index} = step * {index};\n    var step = local_{index} % 7;\n    total += local_{index} + step;\n    total = total - step ** 2;\n    {{ var name = \"inner\"; total++; }}\n}}\n"
+        ));
+    }
+    source
+}
+
+// Tight numeric code on locals declared several blocks out from where they are
+// used, so every read has to find the right enclosing scope.
+fn locals_source() -> String {
+    let mut source = String::from("{\n var a = 1;\n var b = 2;\n var c = 0;\n");
+    source.push_str(&"{\n".repeat(8));
+    for _ in 0..5_000 {
+        source.push_str("c = c + a * b - (a - b) % 3;\na++;\nb += a ~/ 2;\n");
+    }
+    source.push_str(&"}\n".repeat(9));
+    source
+}
+
+// Almost nothing but variable reads, of globals and of locals several scopes
+// out, so the time is mostly spent looking names up.
+fn lookups_source() -> String {
+    let mut source = String::from("var first = 1;\nvar second = 2;\nvar sum = 0;\n");
+    source.push_str("{\n var third = 3;\n");
+    source.push_str(&"{\n".repeat(4));
+    source.push_str("var fourth = 4;\n");
+    let reads = "sum = first + second + third + fourth + first + second + third + fourth;\n";
+    source.push_str(&reads.repeat(5_000));
+    source.push_str(&"}\n".repeat(5));
+    source
+}
+
+fn string_source() -> String {
+    let mut source = String::from("var text = \"\";\n");
+    for index in 0..2_000 {
+        source.push_str(&format!(
+            "text = \"item {index}\" == \"item\" ? text : \"item {index}\";\n"
+        ));
+    }
+    source
+}
+
+fn best(run: impl Fn()) -> Duration {
+    run();
+
+    let mut best = Duration::MAX;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+// Only execution is timed; scanning and parsing happen once up front.
+fn bench(name: &str, source: &str) {
+    let tokens = Scanner::new(source)
+        .scan_tokens()
+        .expect("bench source must parse");
+    let statements = Parser::new(tokens)
+        .parse()
+        .expect("bench source must parse");
+    let time = best(|| {
+        if Interpreter::new().interpret(&statements).is_err() {
+            panic!("benchmark script failed");
+        }
+    });
+    println!("{:<12} best of {}: {:?}", name, ITERATIONS, time);
+}
+
+fn main() {
+    bench("variables", &variables_source());
+    bench("locals", &locals_source());
+    bench("lookups", &lookups_source());
+    bench("strings", &string_source());
+}
@@ -0,0 +1,121 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rlox::compiler::Compiler;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::vm::VirtualMachine;
+
+const ITERATIONS: u32 = 10;
+
+// Counts every allocation so the benchmark can report how many a run makes.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// String values copied around without creating new ones: literals, variable
+// reads and comparisons.
+fn string_copies_source() -> String {
+    let mut source = String::from("var name = \"a fairly long string literal\";\n{\n");
+    for _ in 0..5_000 {
+        source.push_str(
+            "var copy = name;\ncopy == \"a fairly long string literal\";\n\"another literal\";\n",
+        );
+    }
+    source.push_str("}\n");
+    source
+}
+
+fn booleans_source() -> String {
+    let mut source = String::from("var flag = true;\n");
+    for index in 0..5_000 {
+        source.push_str(&format!(
+            "flag = !flag == ({index} < 2500) ? false : true;\n"
+        ));
+    }
+    source
+}
+
+// The allocations of one run and the best time of several, in that order.
+fn measure(run: impl Fn()) -> (usize, Duration) {
+    run();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    run();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    let mut best = Duration::MAX;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        run();
+        best = best.min(start.elapsed());
+    }
+    (allocations, best)
+}
+
+// Only execution is measured; scanning, parsing and compiling happen up front.
+// Both backends run the same script and share a row.
+fn bench(name: &str, source: &str) {
+    let tokens = Scanner::new(source)
+        .scan_tokens()
+        .expect("bench source must parse");
+    let statements = Parser::new(tokens)
+        .parse()
+        .expect("bench source must parse");
+    let tree = measure(|| {
+        if Interpreter::new().interpret(&statements).is_err() {
+            panic!("benchmark script failed");
+        }
+    });
+
+    let chunk = match Compiler::new().compile(&statements) {
+        Ok(chunk) => chunk,
+        Err(_) => panic!("benchmark script failed to compile"),
+    };
+    let vm = measure(|| {
+        if VirtualMachine::new().interpret(&chunk).is_err() {
+            panic!("benchmark script failed");
+        }
+    });
+
+    println!(
+        "{:<14} {:>12} {:>12?} {:>12} {:>12?}",
+        name, tree.0, tree.1, vm.0, vm.1
+    );
+    // The scripts run thousands of statements, none of which should allocate.
+    assert!(
+        tree.0 < 100 && vm.0 < 100,
+        "{} allocates on every statement",
+        name
+    );
+}
+
+fn main() {
+    println!(
+        "{:<14} {:>12} {:>12} {:>12} {:>12}",
+        "", "tree allocs", "tree time", "vm allocs", "vm time"
+    );
+    bench("string copies", &string_copies_source());
+    bench("booleans", &booleans_source());
+}
@@ -1,24 +1,51 @@
 use crate::{
     error::Error,
     expression::{
-        BinaryExpression, Expression, ExpressionVisitor, GroupingExpression, LiteralExpression,
-        UnaryExpression,
+        AssignmentExpression, BinaryExpression, CallExpression, Expression, ExpressionVisitor,
+        GetExpression, GroupingExpression, LiteralExpression, NilCoalescingExpression,
+        TernaryExpression, UnaryExpression, UpdateExpression, VariableExpression,
+    },
+    statement::{
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchStatement,
+        PrintStatement, ReturnStatement, Statement, StatementVisitor, VariableStatement,
     },
 };
 
-pub struct AstPrinter {}
+// Prints the syntax tree as s-expressions, one statement per line, with the
+// statements nested in another indented below it.
+pub struct AstPrinter {
+    output: String,
+    depth: usize,
+}
+
+impl Default for AstPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl AstPrinter {
-    pub fn stringify(&self, expression: &Expression) -> Result<String, Error> {
+    pub fn new() -> Self {
+        Self {
+            output: String::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn print(mut self, statements: &[Statement]) -> Result<String, Error> {
+        for statement in statements {
+            statement.accept(&mut self)?;
+            self.output.push('\n');
+        }
+        Ok(self.output)
+    }
+
+    pub fn stringify(&mut self, expression: &Expression) -> Result<String, Error> {
         expression.accept(self)
     }
 
-    fn parenthesize(
-        &self,
-        operator_lexeme: &str,
-        expressions: &[&Box<Expression>],
-    ) -> Result<String, Error> {
-        let mut builder = format!("({}", operator_lexeme);
+    fn parenthesize(&mut self, name: &str, expressions: &[&Expression]) -> Result<String, Error> {
+        let mut builder = format!("({}", name);
 
         for expression in expressions {
             builder = format!("{} {}", builder, expression.accept(self)?);
@@ -28,25 +55,212 @@ impl AstPrinter {
 
         Ok(builder)
     }
+
+    // Opens a statement node; the statements nested in it go on lines of
+    // their own through `nested`.
+    fn open(&mut self, head: &str) {
+        self.output.push_str(&format!("({}", head));
+    }
+
+    fn nested(&mut self, statement: &Statement) -> Result<(), Error> {
+        self.depth += 1;
+        self.output.push('\n');
+        self.output.push_str(&"    ".repeat(self.depth));
+        let result = statement.accept(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn close(&mut self) {
+        self.output.push(')');
+    }
+}
+
+impl StatementVisitor<()> for AstPrinter {
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) -> Result<(), Error> {
+        let expression = self.stringify(&statement.expression)?;
+        self.output
+            .push_str(&format!("(expression {})", expression));
+        Ok(())
+    }
+
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<(), Error> {
+        let expression = self.stringify(&statement.expression)?;
+        self.output.push_str(&format!("(print {})", expression));
+        Ok(())
+    }
+
+    fn visit_variable_statement(&mut self, statement: &VariableStatement) -> Result<(), Error> {
+        let initializer = self.stringify(&statement.initializer)?;
+        self.output.push_str(&format!(
+            "(var {} {})",
+            statement.identifier.lexeme, initializer
+        ));
+        Ok(())
+    }
+
+    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<(), Error> {
+        self.open("block");
+        for statement in &statement.statements {
+            self.nested(statement)?;
+        }
+        self.close();
+        Ok(())
+    }
+
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<(), Error> {
+        let conditional = self.stringify(&statement.conditional)?;
+        self.open(&format!("if {}", conditional));
+        self.nested(&statement.then_branch)?;
+        if let Some(else_branch) = &statement.else_branch {
+            self.nested(else_branch)?;
+        }
+        self.close();
+        Ok(())
+    }
+
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<(), Error> {
+        let scrutinee = self.stringify(&statement.scrutinee)?;
+        self.open(&format!("match {}", scrutinee));
+        self.depth += 1;
+
+        for arm in &statement.arms {
+            let mut patterns = Vec::new();
+            for pattern in &arm.patterns {
+                patterns.push(self.stringify(pattern)?);
+            }
+            self.output.push('\n');
+            self.output.push_str(&"    ".repeat(self.depth));
+            self.open(&format!("arm ({})", patterns.join(" ")));
+            self.nested(&arm.body)?;
+            self.close();
+        }
+        if let Some(default_branch) = &statement.default_branch {
+            self.output.push('\n');
+            self.output.push_str(&"    ".repeat(self.depth));
+            self.open("default");
+            self.nested(default_branch)?;
+            self.close();
+        }
+
+        self.depth -= 1;
+        self.close();
+        Ok(())
+    }
+
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<(), Error> {
+        let parameters: Vec<String> = statement
+            .parameters
+            .iter()
+            .map(|parameter| parameter.lexeme.to_string())
+            .collect();
+        self.open(&format!(
+            "fun {} ({})",
+            statement.name.lexeme,
+            parameters.join(" ")
+        ));
+        for statement in statement.body.iter() {
+            self.nested(statement)?;
+        }
+        self.close();
+        Ok(())
+    }
+
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<(), Error> {
+        let value = self.stringify(&statement.value)?;
+        self.output.push_str(&format!("(return {})", value));
+        Ok(())
+    }
 }
 
 impl ExpressionVisitor<String> for AstPrinter {
-    fn visit_literal_expression(&self, expression: &LiteralExpression) -> Result<String, Error> {
-        Ok(format!("{}", expression.value))
+    fn visit_literal_expression(
+        &mut self,
+        expression: &LiteralExpression,
+    ) -> Result<String, Error> {
+        Ok(expression.value.to_source())
     }
 
-    fn visit_unary_expression(&self, expression: &UnaryExpression) -> Result<String, Error> {
-        self.parenthesize(&expression.operator.lexeme, &[&expression.right])
+    fn visit_unary_expression(&mut self, expression: &UnaryExpression) -> Result<String, Error> {
+        self.parenthesize(
+            &expression.operator.lexeme.to_string(),
+            &[&expression.right],
+        )
     }
 
-    fn visit_binary_expression(&self, expression: &BinaryExpression) -> Result<String, Error> {
+    fn visit_binary_expression(&mut self, expression: &BinaryExpression) -> Result<String, Error> {
         self.parenthesize(
-            &expression.operator.lexeme,
+            &expression.operator.lexeme.to_string(),
             &[&expression.left, &expression.right],
         )
     }
 
-    fn visit_grouping_expression(&self, expression: &GroupingExpression) -> Result<String, Error> {
+    fn visit_grouping_expression(
+        &mut self,
+        expression: &GroupingExpression,
+    ) -> Result<String, Error> {
         self.parenthesize("group", &[&expression.expressions])
     }
+
+    fn visit_variable_expression(
+        &mut self,
+        expression: &VariableExpression,
+    ) -> Result<String, Error> {
+        Ok(expression.identifier.lexeme.to_string())
+    }
+
+    fn visit_ternary_expression(
+        &mut self,
+        expression: &TernaryExpression,
+    ) -> Result<String, Error> {
+        self.parenthesize(
+            "?:",
+            &[
+                &expression.conditional,
+                &expression.then_branch,
+                &expression.else_branch,
+            ],
+        )
+    }
+
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<String, Error> {
+        self.parenthesize("??", &[&expression.left, &expression.right])
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<String, Error> {
+        let value = self.stringify(&expression.value)?;
+        Ok(format!(
+            "({} {} {})",
+            expression.operator.lexeme, expression.identifier.lexeme, value
+        ))
+    }
+
+    fn visit_update_expression(&mut self, expression: &UpdateExpression) -> Result<String, Error> {
+        let fixity = if expression.prefix {
+            "prefix"
+        } else {
+            "postfix"
+        };
+        Ok(format!(
+            "({} {} {})",
+            fixity, expression.operator.lexeme, expression.identifier.lexeme
+        ))
+    }
+
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<String, Error> {
+        let mut operands = vec![expression.callee.as_ref()];
+        operands.extend(expression.arguments.iter());
+        self.parenthesize("call", &operands)
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<String, Error> {
+        let object = self.stringify(&expression.object)?;
+        Ok(format!("(. {} {})", object, expression.name.lexeme))
+    }
 }
@@ -0,0 +1,101 @@
+use std::{
+    cell::Cell,
+    env, fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    config::{Capability, InterpreterConfig},
+    error::{Error, ErrorType},
+    native::NativeFunction,
+    value::{FromArguments, Value},
+};
+
+type Builtin = fn(&[Value]) -> Result<Value, Error>;
+
+// The native functions an `Interpreter` starts with, by the capability each
+// one needs.
+const BUILTINS: [(Capability, &str, usize, Builtin); 6] = [
+    (Capability::Filesystem, "read_file", 1, read_file),
+    (Capability::Filesystem, "write_file", 2, write_file),
+    (Capability::Environment, "getenv", 1, getenv),
+    (Capability::Process, "exit", 1, exit),
+    (Capability::Clock, "clock", 0, clock),
+    (Capability::Random, "random", 0, random),
+];
+
+// The builtins a backend defines as globals. One the configuration does not
+// allow is still there, so a script using it learns why it can't.
+pub(crate) fn natives(config: &InterpreterConfig) -> Vec<NativeFunction> {
+    BUILTINS
+        .into_iter()
+        .map(|(capability, name, arity, function)| {
+            if config.allows(capability) {
+                NativeFunction::new(name, arity, function)
+            } else {
+                NativeFunction::new(name, arity, move |_| {
+                    Err(Error::new(
+                        None,
+                        ErrorType::PermissionError,
+                        &format!("{} requires the {} capability", name, capability),
+                    ))
+                })
+            }
+        })
+        .collect()
+}
+
+fn read_file(arguments: &[Value]) -> Result<Value, Error> {
+    let (path,): (String,) = FromArguments::from_arguments(arguments)?;
+    fs::read_to_string(&path)
+        .map(Value::from)
+        .map_err(|error| Error::runtime(&format!("Could not read {}: {}", path, error)))
+}
+
+fn write_file(arguments: &[Value]) -> Result<Value, Error> {
+    let (path, contents): (String, String) = FromArguments::from_arguments(arguments)?;
+    fs::write(&path, contents)
+        .map(|_| Value::Nil)
+        .map_err(|error| Error::runtime(&format!("Could not write {}: {}", path, error)))
+}
+
+fn getenv(arguments: &[Value]) -> Result<Value, Error> {
+    let (name,): (String,) = FromArguments::from_arguments(arguments)?;
+    Ok(env::var(name).ok().into())
+}
+
+fn exit(arguments: &[Value]) -> Result<Value, Error> {
+    let (code,): (f64,) = FromArguments::from_arguments(arguments)?;
+    Err(Error::exit(code as i32))
+}
+
+// Seconds since the Unix epoch.
+fn clock(_arguments: &[Value]) -> Result<Value, Error> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
+
+thread_local! {
+    static SEED: Cell<u64> = const { Cell::new(0) };
+}
+
+// A number in [0, 1) from a xorshift generator seeded by the clock. Good
+// enough for scripts, not for anything that needs to be unpredictable.
+fn random(_arguments: &[Value]) -> Result<Value, Error> {
+    let mut state = SEED.get();
+    if state == 0 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        state = now.as_nanos() as u64 | 1;
+    }
+
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    SEED.set(state);
+
+    Ok(Value::Number((state >> 11) as f64 / (1u64 << 53) as f64))
+}
@@ -0,0 +1,193 @@
+use std::rc::Rc;
+
+use crate::{interner::Symbol, object::Object, operation};
+
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    Dup,
+
+    DefineGlobal,
+    GetGlobal,
+    SetGlobal,
+    GetLocal,
+    SetLocal,
+    GetUpvalue,
+    SetUpvalue,
+    CloseUpvalue,
+
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    FloorDivide,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+
+    Negate,
+    Not,
+    BitwiseNot,
+
+    Print,
+    Call,
+    GetProperty,
+    Closure,
+    Jump,
+    JumpIfFalse,
+    JumpIfNotNil,
+    Return,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 43] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::Dup,
+        OpCode::DefineGlobal,
+        OpCode::GetGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::CloseUpvalue,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Modulo,
+        OpCode::Power,
+        OpCode::FloorDivide,
+        OpCode::BitwiseAnd,
+        OpCode::BitwiseOr,
+        OpCode::BitwiseXor,
+        OpCode::ShiftLeft,
+        OpCode::ShiftRight,
+        OpCode::Equal,
+        OpCode::NotEqual,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Negate,
+        OpCode::Not,
+        OpCode::BitwiseNot,
+        OpCode::Print,
+        OpCode::Call,
+        OpCode::GetProperty,
+        OpCode::Closure,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::JumpIfNotNil,
+        OpCode::Return,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        Self::ALL.get(byte as usize).copied()
+    }
+}
+
+// A compiled program: the instruction stream, the constants, global names and
+// functions it refers to by index and a run-length encoded table mapping
+// instructions back to source lines.
+#[derive(Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Object>,
+    pub names: Vec<Symbol>,
+    pub functions: Vec<Rc<CompiledFunction>>,
+    // (line, end offset) pairs, one per run of instructions from the same line.
+    lines: Vec<(usize, usize)>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self {
+            code: Vec::new(),
+            constants: Vec::new(),
+            names: Vec::new(),
+            functions: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn write(&mut self, byte: u8, line: usize) {
+        self.code.push(byte);
+
+        let end = self.code.len();
+        match self.lines.last_mut() {
+            Some((last_line, last_end)) if *last_line == line => *last_end = end,
+            _ => self.lines.push((line, end)),
+        }
+    }
+
+    pub fn add_constant(&mut self, value: Object) -> usize {
+        // String literals repeat a lot, so equal ones share a slot.
+        if let Object::String(_) = value {
+            if let Some(index) = self.constants.iter().position(|constant| {
+                matches!(constant, Object::String(_)) && operation::is_equal(constant, &value)
+            }) {
+                return index;
+            }
+        }
+
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    pub fn add_name(&mut self, name: Symbol) -> usize {
+        if let Some(index) = self.names.iter().position(|existing| *existing == name) {
+            return index;
+        }
+
+        self.names.push(name);
+        self.names.len() - 1
+    }
+
+    // The VM asks for the line of every instruction that can fail, so this is
+    // a binary search rather than a walk over the runs.
+    pub fn line(&self, offset: usize) -> usize {
+        let run = self.lines.partition_point(|(_, end)| *end <= offset);
+        self.lines
+            .get(run)
+            .or(self.lines.last())
+            .map(|(line, _)| *line)
+            .unwrap_or(0)
+    }
+}
+
+// A function declaration compiled into a chunk of its own. Its parameters are
+// the first locals of the frame a call sets up.
+pub struct CompiledFunction {
+    pub name: Symbol,
+    pub arity: usize,
+    pub chunk: Chunk,
+    pub captures: Vec<Capture>,
+}
+
+// Where a closure finds each variable it captures when it is created: in a
+// stack slot of the function creating it, or among that function's captures.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Capture {
+    Local(u16),
+    Upvalue(u16),
+}
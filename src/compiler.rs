@@ -0,0 +1,615 @@
+use std::{mem, rc::Rc};
+
+use crate::{
+    chunk::{Capture, Chunk, CompiledFunction, OpCode},
+    error::{Error, ErrorType},
+    expression::{
+        AssignmentExpression, BinaryExpression, CallExpression, ExpressionVisitor, GetExpression,
+        GroupingExpression, LiteralExpression, NilCoalescingExpression, TernaryExpression,
+        UnaryExpression, UpdateExpression, VariableExpression,
+    },
+    interner::Symbol,
+    object::Object,
+    statement::{
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchStatement,
+        PrintStatement, ReturnStatement, Statement, StatementVisitor, VariableStatement,
+    },
+    token::Token,
+    token_type::TokenType,
+};
+
+struct Local {
+    name: Symbol,
+    depth: usize,
+    // Captured locals are moved off the stack when their block ends.
+    captured: bool,
+}
+
+// What is set aside of a function while one declared inside it compiles.
+struct Enclosing {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    captures: Vec<Capture>,
+    scope_depth: usize,
+}
+
+// Compiles the statements produced by the `Parser` into a single `Chunk`.
+// Variables declared at the top level are globals looked up by name, while
+// variables declared inside blocks and functions live in fixed stack slots.
+// Functions compile into chunks of their own, and a variable a function uses
+// from an enclosing one is reached through an upvalue.
+pub struct Compiler {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    captures: Vec<Capture>,
+    scope_depth: usize,
+    enclosing: Vec<Enclosing>,
+    line: usize,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            chunk: Chunk::new(),
+            locals: Vec::new(),
+            captures: Vec::new(),
+            scope_depth: 0,
+            enclosing: Vec::new(),
+            line: 1,
+        }
+    }
+
+    pub fn compile(mut self, statements: &[Statement]) -> Result<Chunk, Error> {
+        for statement in statements {
+            statement.accept(&mut self)?;
+        }
+        self.emit_op(OpCode::Return, self.line);
+
+        Ok(self.chunk)
+    }
+
+    fn emit_op(&mut self, opcode: OpCode, line: usize) {
+        self.line = line;
+        self.chunk.write(opcode as u8, line);
+    }
+
+    fn emit_operand(&mut self, operand: u16) {
+        for byte in operand.to_be_bytes() {
+            self.chunk.write(byte, self.line);
+        }
+    }
+
+    fn make_constant(&mut self, value: Object) -> Result<u16, Error> {
+        let index = self.chunk.add_constant(value);
+        u16::try_from(index).map_err(|_| {
+            Error::new(
+                self.line,
+                ErrorType::CompileError,
+                "Too many constants in one chunk",
+            )
+        })
+    }
+
+    fn make_name(&mut self, identifier: &Token) -> Result<u16, Error> {
+        let index = self.chunk.add_name(identifier.lexeme);
+        u16::try_from(index).map_err(|_| {
+            Error::new(
+                identifier.line,
+                ErrorType::CompileError,
+                "Too many global names in one chunk",
+            )
+        })
+    }
+
+    fn emit_constant(&mut self, value: Object) -> Result<(), Error> {
+        let index = self.make_constant(value)?;
+        self.emit_op(OpCode::Constant, self.line);
+        self.emit_operand(index);
+        Ok(())
+    }
+
+    fn emit_jump(&mut self, opcode: OpCode) -> usize {
+        self.emit_op(opcode, self.line);
+        self.emit_operand(u16::MAX);
+        self.chunk.code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize) -> Result<(), Error> {
+        let jump = u16::try_from(self.chunk.code.len() - offset - 2).map_err(|_| {
+            Error::new(
+                self.line,
+                ErrorType::CompileError,
+                "Too much code to jump over",
+            )
+        })?;
+        let [high, low] = jump.to_be_bytes();
+        self.chunk.code[offset] = high;
+        self.chunk.code[offset + 1] = low;
+        Ok(())
+    }
+
+    fn resolve_local(&self, name: Symbol) -> Option<u16> {
+        self.locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u16)
+    }
+
+    // The functions being compiled are numbered from the outermost, the
+    // script, which is 0, to the current one.
+    fn locals_at(&mut self, level: usize) -> &mut Vec<Local> {
+        match self.enclosing.get_mut(level) {
+            Some(enclosing) => &mut enclosing.locals,
+            None => &mut self.locals,
+        }
+    }
+
+    fn captures_at(&mut self, level: usize) -> &mut Vec<Capture> {
+        match self.enclosing.get_mut(level) {
+            Some(enclosing) => &mut enclosing.captures,
+            None => &mut self.captures,
+        }
+    }
+
+    // Finds `name` in the functions enclosing the one at `level` and returns
+    // the index of the upvalue through which that function reaches it.
+    fn resolve_capture(&mut self, level: usize, identifier: &Token) -> Result<Option<u16>, Error> {
+        if level == 0 {
+            return Ok(None);
+        }
+
+        let locals = self.locals_at(level - 1);
+        let capture = match locals
+            .iter()
+            .rposition(|local| local.name == identifier.lexeme)
+        {
+            Some(slot) => {
+                locals[slot].captured = true;
+                Capture::Local(slot as u16)
+            }
+            None => match self.resolve_capture(level - 1, identifier)? {
+                Some(index) => Capture::Upvalue(index),
+                None => return Ok(None),
+            },
+        };
+
+        let captures = self.captures_at(level);
+        let index = match captures.iter().position(|existing| *existing == capture) {
+            Some(index) => index,
+            None => {
+                captures.push(capture);
+                captures.len() - 1
+            }
+        };
+        u16::try_from(index).map(Some).map_err(|_| {
+            Error::new(
+                identifier.line,
+                ErrorType::CompileError,
+                "Too many captured variables in one function",
+            )
+        })
+    }
+
+    fn emit_get(&mut self, identifier: &Token) -> Result<(), Error> {
+        if let Some(slot) = self.resolve_local(identifier.lexeme) {
+            self.emit_op(OpCode::GetLocal, identifier.line);
+            self.emit_operand(slot);
+        } else if let Some(index) = self.resolve_capture(self.enclosing.len(), identifier)? {
+            self.emit_op(OpCode::GetUpvalue, identifier.line);
+            self.emit_operand(index);
+        } else {
+            let name = self.make_name(identifier)?;
+            self.emit_op(OpCode::GetGlobal, identifier.line);
+            self.emit_operand(name);
+        }
+        Ok(())
+    }
+
+    fn emit_set(&mut self, identifier: &Token) -> Result<(), Error> {
+        if let Some(slot) = self.resolve_local(identifier.lexeme) {
+            self.emit_op(OpCode::SetLocal, identifier.line);
+            self.emit_operand(slot);
+        } else if let Some(index) = self.resolve_capture(self.enclosing.len(), identifier)? {
+            self.emit_op(OpCode::SetUpvalue, identifier.line);
+            self.emit_operand(index);
+        } else {
+            let name = self.make_name(identifier)?;
+            self.emit_op(OpCode::SetGlobal, identifier.line);
+            self.emit_operand(name);
+        }
+        Ok(())
+    }
+
+    // Inside a block, makes room for a new local, or returns the slot of the
+    // one it redeclares in the same block, which is reused.
+    fn declare_local(&mut self, identifier: &Token) -> Result<Option<u16>, Error> {
+        if self.scope_depth == 0 {
+            return Ok(None);
+        }
+
+        let redeclared = self
+            .locals
+            .iter()
+            .rposition(|local| local.depth == self.scope_depth && local.name == identifier.lexeme);
+        if let Some(slot) = redeclared {
+            return Ok(Some(slot as u16));
+        }
+
+        if self.locals.len() > u16::MAX as usize {
+            return Err(Error::new(
+                identifier.line,
+                ErrorType::CompileError,
+                "Too many local variables",
+            ));
+        }
+        self.locals.push(Local {
+            name: identifier.lexeme,
+            depth: self.scope_depth,
+            captured: false,
+        });
+        Ok(None)
+    }
+
+    // Stores the value on top of the stack in a variable just declared. A new
+    // local needs nothing more, since its slot is where the value already is.
+    fn define_variable(
+        &mut self,
+        identifier: &Token,
+        redeclared: Option<u16>,
+    ) -> Result<(), Error> {
+        if self.scope_depth == 0 {
+            let name = self.make_name(identifier)?;
+            self.emit_op(OpCode::DefineGlobal, identifier.line);
+            self.emit_operand(name);
+        } else if let Some(slot) = redeclared {
+            self.emit_op(OpCode::SetLocal, identifier.line);
+            self.emit_operand(slot);
+            self.emit_op(OpCode::Pop, identifier.line);
+        }
+        Ok(())
+    }
+
+    // Compiles a function body into a chunk of its own and returns its index
+    // among the functions of the current chunk.
+    fn compile_function(&mut self, statement: &FunctionStatement) -> Result<u16, Error> {
+        self.enclosing.push(Enclosing {
+            chunk: mem::take(&mut self.chunk),
+            locals: mem::take(&mut self.locals),
+            captures: mem::take(&mut self.captures),
+            scope_depth: mem::replace(&mut self.scope_depth, 1),
+        });
+        for parameter in &statement.parameters {
+            self.locals.push(Local {
+                name: parameter.lexeme,
+                depth: 1,
+                captured: false,
+            });
+        }
+
+        let compiled = statement
+            .body
+            .iter()
+            .try_for_each(|statement| statement.accept(self));
+        // Falling off the end of the body returns nil.
+        self.emit_op(OpCode::Nil, self.line);
+        self.emit_op(OpCode::Return, self.line);
+
+        let enclosing = self
+            .enclosing
+            .pop()
+            .expect("function compiled outside of a chunk");
+        let chunk = mem::replace(&mut self.chunk, enclosing.chunk);
+        let captures = mem::replace(&mut self.captures, enclosing.captures);
+        self.locals = enclosing.locals;
+        self.scope_depth = enclosing.scope_depth;
+        compiled?;
+
+        self.chunk.functions.push(Rc::new(CompiledFunction {
+            name: statement.name.lexeme,
+            arity: statement.parameters.len(),
+            chunk,
+            captures,
+        }));
+        u16::try_from(self.chunk.functions.len() - 1).map_err(|_| {
+            Error::new(
+                statement.name.line,
+                ErrorType::CompileError,
+                "Too many functions in one chunk",
+            )
+        })
+    }
+
+    fn binary_opcode(&self, operator: &Token) -> Result<OpCode, Error> {
+        match operator.ttype {
+            TokenType::Plus => Ok(OpCode::Add),
+            TokenType::Minus => Ok(OpCode::Subtract),
+            TokenType::Star => Ok(OpCode::Multiply),
+            TokenType::Slash => Ok(OpCode::Divide),
+            TokenType::Percent => Ok(OpCode::Modulo),
+            TokenType::StarStar => Ok(OpCode::Power),
+            TokenType::TildeSlash => Ok(OpCode::FloorDivide),
+            TokenType::Ampersand => Ok(OpCode::BitwiseAnd),
+            TokenType::Pipe => Ok(OpCode::BitwiseOr),
+            TokenType::Caret => Ok(OpCode::BitwiseXor),
+            TokenType::LessLess => Ok(OpCode::ShiftLeft),
+            TokenType::GreaterGreater => Ok(OpCode::ShiftRight),
+            TokenType::EqualEqual => Ok(OpCode::Equal),
+            TokenType::BangEqual => Ok(OpCode::NotEqual),
+            TokenType::Greater => Ok(OpCode::Greater),
+            TokenType::GreaterEqual => Ok(OpCode::GreaterEqual),
+            TokenType::Less => Ok(OpCode::Less),
+            TokenType::LessEqual => Ok(OpCode::LessEqual),
+            _ => Err(Error::new(
+                operator.line,
+                ErrorType::CompileError,
+                "Operator does not support binary opertaion",
+            )),
+        }
+    }
+}
+
+impl StatementVisitor<()> for Compiler {
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) -> Result<(), Error> {
+        statement.expression.accept(self)?;
+        self.emit_op(OpCode::Pop, self.line);
+        Ok(())
+    }
+
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<(), Error> {
+        statement.expression.accept(self)?;
+        self.emit_op(OpCode::Print, statement.keyword.line);
+        Ok(())
+    }
+
+    fn visit_variable_statement(&mut self, statement: &VariableStatement) -> Result<(), Error> {
+        statement.initializer.accept(self)?;
+        let redeclared = self.declare_local(&statement.identifier)?;
+        self.define_variable(&statement.identifier, redeclared)
+    }
+
+    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<(), Error> {
+        self.scope_depth += 1;
+        for statement in &statement.statements {
+            statement.accept(self)?;
+        }
+        self.scope_depth -= 1;
+
+        while let Some(local) = self.locals.pop_if(|local| local.depth > self.scope_depth) {
+            let opcode = if local.captured {
+                OpCode::CloseUpvalue
+            } else {
+                OpCode::Pop
+            };
+            self.emit_op(opcode, self.line);
+        }
+
+        Ok(())
+    }
+
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<(), Error> {
+        statement.conditional.accept(self)?;
+
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop, self.line);
+        statement.then_branch.accept(self)?;
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump)?;
+        self.emit_op(OpCode::Pop, self.line);
+        if let Some(else_branch) = &statement.else_branch {
+            else_branch.accept(self)?;
+        }
+        self.patch_jump(end_jump)
+    }
+
+    // The scrutinee stays on top of the stack while the patterns are compared
+    // against it and is popped before the chosen arm runs.
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<(), Error> {
+        statement.scrutinee.accept(self)?;
+
+        let mut end_jumps = Vec::new();
+        for arm in &statement.arms {
+            let mut body_jumps = Vec::new();
+            for pattern in &arm.patterns {
+                self.emit_op(OpCode::Dup, self.line);
+                pattern.accept(self)?;
+                self.emit_op(OpCode::Equal, self.line);
+                let next_pattern = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop, self.line);
+                body_jumps.push(self.emit_jump(OpCode::Jump));
+                self.patch_jump(next_pattern)?;
+                self.emit_op(OpCode::Pop, self.line);
+            }
+            let next_arm = self.emit_jump(OpCode::Jump);
+
+            for body_jump in body_jumps {
+                self.patch_jump(body_jump)?;
+            }
+            self.emit_op(OpCode::Pop, self.line);
+            arm.body.accept(self)?;
+            end_jumps.push(self.emit_jump(OpCode::Jump));
+
+            self.patch_jump(next_arm)?;
+        }
+
+        self.emit_op(OpCode::Pop, self.line);
+        if let Some(default_branch) = &statement.default_branch {
+            default_branch.accept(self)?;
+        }
+
+        for end_jump in end_jumps {
+            self.patch_jump(end_jump)?;
+        }
+        Ok(())
+    }
+
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<(), Error> {
+        // The name is declared before the body compiles, so the function can
+        // call itself.
+        let redeclared = self.declare_local(&statement.name)?;
+        let index = self.compile_function(statement)?;
+        self.emit_op(OpCode::Closure, statement.name.line);
+        self.emit_operand(index);
+        self.define_variable(&statement.name, redeclared)
+    }
+
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<(), Error> {
+        statement.value.accept(self)?;
+        self.emit_op(OpCode::Return, statement.keyword.line);
+        Ok(())
+    }
+}
+
+impl ExpressionVisitor<()> for Compiler {
+    fn visit_literal_expression(&mut self, expression: &LiteralExpression) -> Result<(), Error> {
+        match &expression.value {
+            Object::Nil => self.emit_op(OpCode::Nil, self.line),
+            Object::Bool(true) => self.emit_op(OpCode::True, self.line),
+            Object::Bool(false) => self.emit_op(OpCode::False, self.line),
+            value => self.emit_constant(*value)?,
+        }
+        Ok(())
+    }
+
+    fn visit_unary_expression(&mut self, expression: &UnaryExpression) -> Result<(), Error> {
+        expression.right.accept(self)?;
+
+        let operator = &expression.operator;
+        let opcode = match operator.ttype {
+            TokenType::Minus => OpCode::Negate,
+            TokenType::Bang => OpCode::Not,
+            TokenType::Tilde => OpCode::BitwiseNot,
+            _ => {
+                return Err(Error::new(
+                    operator.line,
+                    ErrorType::CompileError,
+                    "Operator does not support unary operation",
+                ))
+            }
+        };
+        self.emit_op(opcode, operator.line);
+        Ok(())
+    }
+
+    fn visit_binary_expression(&mut self, expression: &BinaryExpression) -> Result<(), Error> {
+        expression.left.accept(self)?;
+        expression.right.accept(self)?;
+
+        let opcode = self.binary_opcode(&expression.operator)?;
+        self.emit_op(opcode, expression.operator.line);
+        Ok(())
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &GroupingExpression) -> Result<(), Error> {
+        expression.expressions.accept(self)
+    }
+
+    fn visit_variable_expression(&mut self, expression: &VariableExpression) -> Result<(), Error> {
+        self.emit_get(&expression.identifier)
+    }
+
+    fn visit_ternary_expression(&mut self, expression: &TernaryExpression) -> Result<(), Error> {
+        expression.conditional.accept(self)?;
+
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop, self.line);
+        expression.then_branch.accept(self)?;
+        let end_jump = self.emit_jump(OpCode::Jump);
+
+        self.patch_jump(else_jump)?;
+        self.emit_op(OpCode::Pop, self.line);
+        expression.else_branch.accept(self)?;
+        self.patch_jump(end_jump)
+    }
+
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<(), Error> {
+        expression.left.accept(self)?;
+
+        let end_jump = self.emit_jump(OpCode::JumpIfNotNil);
+        self.emit_op(OpCode::Pop, expression.operator.line);
+        expression.right.accept(self)?;
+        self.patch_jump(end_jump)
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<(), Error> {
+        let operator = &expression.operator;
+
+        if operator.ttype == TokenType::Equal {
+            expression.value.accept(self)?;
+        } else {
+            self.emit_get(&expression.identifier)?;
+            expression.value.accept(self)?;
+            let opcode = match operator.ttype {
+                TokenType::PlusEqual => OpCode::Add,
+                TokenType::MinusEqual => OpCode::Subtract,
+                TokenType::StarEqual => OpCode::Multiply,
+                _ => OpCode::Divide,
+            };
+            self.emit_op(opcode, operator.line);
+        }
+
+        self.emit_set(&expression.identifier)
+    }
+
+    // Postfix forms keep a copy of the old value underneath the updated one.
+    fn visit_update_expression(&mut self, expression: &UpdateExpression) -> Result<(), Error> {
+        let operator = &expression.operator;
+
+        self.emit_get(&expression.identifier)?;
+        if !expression.prefix {
+            self.emit_op(OpCode::Dup, operator.line);
+        }
+        self.emit_constant(Object::Number(1.0))?;
+        let opcode = match operator.ttype {
+            TokenType::PlusPlus => OpCode::Add,
+            _ => OpCode::Subtract,
+        };
+        self.emit_op(opcode, operator.line);
+        self.emit_set(&expression.identifier)?;
+        if !expression.prefix {
+            self.emit_op(OpCode::Pop, operator.line);
+        }
+
+        Ok(())
+    }
+
+    // The callee sits on the stack below its arguments, and the call leaves
+    // only the result in their place.
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<(), Error> {
+        expression.callee.accept(self)?;
+        for argument in &expression.arguments {
+            argument.accept(self)?;
+        }
+
+        let count = u16::try_from(expression.arguments.len()).map_err(|_| {
+            Error::new(
+                expression.paren.line,
+                ErrorType::CompileError,
+                "Too many arguments in one call",
+            )
+        })?;
+        self.emit_op(OpCode::Call, expression.paren.line);
+        self.emit_operand(count);
+        Ok(())
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<(), Error> {
+        expression.object.accept(self)?;
+        let name = self.make_name(&expression.name)?;
+        self.emit_op(OpCode::GetProperty, expression.name.line);
+        self.emit_operand(name);
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use crate::limits::Limits;
+
+// A group of native functions that reach outside the interpreter. Scripts
+// only get the ones the host allows; the others are still defined, but fail
+// with a `PermissionError` when called.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Capability {
+    Filesystem,
+    Environment,
+    Process,
+    Clock,
+    Random,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Capability::Filesystem,
+        Capability::Environment,
+        Capability::Process,
+        Capability::Clock,
+        Capability::Random,
+    ];
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capability::Filesystem => write!(f, "filesystem"),
+            Capability::Environment => write!(f, "environment"),
+            Capability::Process => write!(f, "process"),
+            Capability::Clock => write!(f, "clock"),
+            Capability::Random => write!(f, "random"),
+        }
+    }
+}
+
+// What an `Interpreter` lets the scripts it runs do. The default is meant for
+// untrusted code: no capabilities, `print` allowed and the default `Limits`.
+#[derive(Clone)]
+pub struct InterpreterConfig {
+    capabilities: Vec<Capability>,
+    pub(crate) print: bool,
+    pub(crate) limits: Limits,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterpreterConfig {
+    pub fn new() -> Self {
+        Self {
+            capabilities: Vec::new(),
+            print: true,
+            limits: Limits::default(),
+        }
+    }
+
+    // Everything allowed, for scripts that are as trusted as the host.
+    pub fn trusted() -> Self {
+        Self::new().allow_all()
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        if !self.capabilities.contains(&capability) {
+            self.capabilities.push(capability);
+        }
+        self
+    }
+
+    pub fn allow_all(self) -> Self {
+        Capability::ALL
+            .into_iter()
+            .fold(self, |config, capability| config.allow(capability))
+    }
+
+    pub fn deny(mut self, capability: Capability) -> Self {
+        self.capabilities.retain(|allowed| *allowed != capability);
+        self
+    }
+
+    pub fn print(mut self, allowed: bool) -> Self {
+        self.print = allowed;
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
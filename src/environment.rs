@@ -1,41 +1,139 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, mem};
 
 use crate::{
     error::{Error, ErrorType},
+    expression::Binding,
+    heap::{self, Handle},
+    interner::Symbol,
     object::Object,
-    token::Token,
 };
 
+// Globals are looked up by name, while block locals live in one heap frame per
+// active block and are addressed by the (depth, slot) the `Resolver` assigned.
+// A function runs in the frames it captured where it was declared, plus one
+// for its own body; the caller's frames are set aside until it returns.
+#[derive(Default)]
 pub struct Environment {
-    values: HashMap<String, Object>,
+    globals: HashMap<Symbol, Object>,
+    frames: Vec<Handle>,
+    suspended: Vec<Vec<Handle>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
-            values: HashMap::new(),
+            globals: HashMap::new(),
+            frames: Vec::new(),
+            suspended: Vec::new(),
         }
     }
 
-    pub fn clone(&self) -> Self {
-        Self {
-            values: self.values.clone(),
+    pub fn push_frame(&mut self) -> Result<(), Error> {
+        self.frames.push(heap::allocate_frame()?);
+        Ok(())
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.frames.pop();
+    }
+
+    pub fn capture(&self) -> Vec<Handle> {
+        self.frames.clone()
+    }
+
+    pub fn enter_call(&mut self, captured: &[Handle]) -> Result<(), Error> {
+        let frame = heap::allocate_frame()?;
+        let frames = mem::replace(&mut self.frames, captured.to_vec());
+        self.suspended.push(frames);
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    pub fn exit_call(&mut self) {
+        self.frames = self.suspended.pop().expect("call exited without entering");
+    }
+
+    pub fn define(&mut self, binding: Binding, identifier: Symbol, value: Object) {
+        match binding {
+            Binding::Global => {
+                self.globals.insert(identifier, value);
+            }
+            Binding::Local { slot, .. } => {
+                let frame = *self
+                    .frames
+                    .last()
+                    .expect("local defined outside of a block");
+                heap::define_frame_slot(frame, slot, value);
+            }
         }
     }
 
-    pub fn define(&mut self, identifier: &str, value: Object) {
-        self.values.insert(identifier.to_string(), value);
+    pub fn access(
+        &self,
+        binding: Binding,
+        identifier: Symbol,
+        line: usize,
+    ) -> Result<Object, Error> {
+        let value = match binding {
+            Binding::Global => self.globals.get(&identifier).copied(),
+            Binding::Local { depth, slot } => self
+                .frame(depth)
+                .and_then(|frame| heap::frame_slot(frame, slot)),
+        };
+
+        value.ok_or_else(|| undefined_variable(identifier, line))
+    }
+
+    pub fn global(&self, identifier: Symbol) -> Option<Object> {
+        self.globals.get(&identifier).copied()
     }
 
-    pub fn access(&self, identifier: &Token) -> Result<Object, Error> {
-        if let Some(value) = self.values.get(&identifier.lexeme) {
-            Ok(value.clone())
+    pub fn assign(
+        &mut self,
+        binding: Binding,
+        identifier: Symbol,
+        line: usize,
+        value: Object,
+    ) -> Result<(), Error> {
+        let assigned = match binding {
+            Binding::Global => match self.globals.get_mut(&identifier) {
+                Some(target) => {
+                    *target = value;
+                    true
+                }
+                None => false,
+            },
+            Binding::Local { depth, slot } => self
+                .frame(depth)
+                .is_some_and(|frame| heap::set_frame_slot(frame, slot, value)),
+        };
+
+        if assigned {
+            Ok(())
         } else {
-            Err(Error::new(
-                identifier.line,
-                ErrorType::RuntimeError,
-                &format!("Undefined variable {}", identifier.lexeme),
-            ))
+            Err(undefined_variable(identifier, line))
         }
     }
+
+    // Every value reachable from here must survive a collection.
+    pub fn roots(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.globals
+            .values()
+            .filter_map(Object::handle)
+            .chain(self.frames.iter().copied())
+            .chain(self.suspended.iter().flatten().copied())
+    }
+
+    fn frame(&self, depth: usize) -> Option<Handle> {
+        let index = self.frames.len().checked_sub(depth + 1)?;
+        self.frames.get(index).copied()
+    }
+}
+
+fn undefined_variable(identifier: Symbol, line: usize) -> Error {
+    Error::new(
+        line,
+        ErrorType::RuntimeError,
+        &format!("Undefined variable {}", identifier),
+    )
 }
@@ -1,9 +1,21 @@
-use std::fmt;
+use std::{
+    fmt,
+    io::{self, Write},
+};
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ErrorType {
     LexingError,
     ParsingError,
+    ParsingWarning,
+    CompileError,
+    FormatError,
     RuntimeError,
+    LimitError,
+    MemoryError,
+    PermissionError,
+    // Not a failure: the script asked to stop, with `exit_status`.
+    Exit,
 }
 
 impl fmt::Display for ErrorType {
@@ -11,30 +23,103 @@ impl fmt::Display for ErrorType {
         match self {
             ErrorType::LexingError => write!(f, "LexingError"),
             ErrorType::ParsingError => write!(f, "ParsingError"),
+            ErrorType::ParsingWarning => write!(f, "ParsingWarning"),
+            ErrorType::CompileError => write!(f, "CompileError"),
+            ErrorType::FormatError => write!(f, "FormatError"),
             ErrorType::RuntimeError => write!(f, "RuntimeError"),
+            ErrorType::LimitError => write!(f, "LimitError"),
+            ErrorType::MemoryError => write!(f, "MemoryError"),
+            ErrorType::PermissionError => write!(f, "PermissionError"),
+            ErrorType::Exit => write!(f, "Exit"),
         }
     }
 }
 
 pub struct Error {
-    line: usize,
+    // None when nothing in the source led to the error, as when the host calls
+    // a function that does not exist.
+    line: Option<usize>,
     etype: ErrorType,
     message: String,
+    status: i32,
 }
 
 impl Error {
-    pub fn new(line: usize, etype: ErrorType, message: &str) -> Error {
+    pub fn new(line: impl Into<Option<usize>>, etype: ErrorType, message: &str) -> Error {
         Error {
-            line,
+            line: line.into(),
             etype,
             message: message.to_string(),
+            status: 0,
         }
     }
 
+    // Unwinds a running script the way an error does, so every frame is left
+    // and the host decides what stopping means; only the CLI exits the process.
+    pub fn exit(status: i32) -> Error {
+        Error {
+            status,
+            ..Error::new(
+                None,
+                ErrorType::Exit,
+                &format!("Script exited with status {}", status),
+            )
+        }
+    }
+
+    // A runtime error raised by host code, such as a native function. The
+    // interpreter fills in the line of the call that led to it, if any.
+    pub fn runtime(message: &str) -> Error {
+        Error::new(None, ErrorType::RuntimeError, message)
+    }
+
+    pub(crate) fn at_line(mut self, line: Option<usize>) -> Error {
+        self.line = self.line.or(line);
+        self
+    }
+
+    pub fn etype(&self) -> ErrorType {
+        self.etype
+    }
+
+    pub fn exit_status(&self) -> Option<i32> {
+        (self.etype == ErrorType::Exit).then_some(self.status)
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
     pub fn report(&self, loc: &str) {
-        eprintln!(
-            "[line {}] {}{}: {}",
-            self.line, self.etype, loc, self.message
-        );
+        // There is nowhere left to report a failure to write to stderr.
+        let _ = self.report_to(&mut io::stderr(), loc);
+    }
+
+    pub fn report_to(&self, out: &mut dyn Write, loc: &str) -> io::Result<()> {
+        if let Some(line) = self.line {
+            write!(out, "[line {}] ", line)?;
+        }
+        writeln!(out, "{}{}: {}", self.etype, loc, self.message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "[line {}] ", line)?;
+        }
+        write!(f, "{}: {}", self.etype, self.message)
     }
 }
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
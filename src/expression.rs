@@ -1,3 +1,5 @@
+use std::cell::Cell;
+
 use crate::error::Error;
 use crate::object::Object;
 use crate::token::Token;
@@ -8,6 +10,20 @@ pub enum Expression {
     Binary(BinaryExpression),
     Grouping(GroupingExpression),
     VariableExpression(VariableExpression),
+    Ternary(TernaryExpression),
+    NilCoalescing(NilCoalescingExpression),
+    Assignment(AssignmentExpression),
+    Update(UpdateExpression),
+    Call(CallExpression),
+    Get(GetExpression),
+}
+
+// Where a variable lives at runtime. The parser leaves every binding global and
+// the `Resolver` rewrites the ones that refer to block locals.
+#[derive(Clone, Copy)]
+pub enum Binding {
+    Global,
+    Local { depth: usize, slot: usize },
 }
 
 pub struct LiteralExpression {
@@ -31,26 +47,83 @@ pub struct GroupingExpression {
 
 pub struct VariableExpression {
     pub identifier: Token,
+    pub binding: Cell<Binding>,
+}
+
+pub struct TernaryExpression {
+    pub conditional: Box<Expression>,
+    pub then_branch: Box<Expression>,
+    pub else_branch: Box<Expression>,
+}
+
+pub struct NilCoalescingExpression {
+    pub left: Box<Expression>,
+    pub operator: Token,
+    pub right: Box<Expression>,
+}
+
+pub struct AssignmentExpression {
+    pub identifier: Token,
+    pub operator: Token,
+    pub value: Box<Expression>,
+    pub binding: Cell<Binding>,
+}
+
+pub struct UpdateExpression {
+    pub identifier: Token,
+    pub operator: Token,
+    pub prefix: bool,
+    pub binding: Cell<Binding>,
+}
+
+pub struct CallExpression {
+    pub callee: Box<Expression>,
+    // The closing parenthesis, whose line is reported for errors in the call.
+    pub paren: Token,
+    pub arguments: Vec<Expression>,
+}
+
+pub struct GetExpression {
+    pub object: Box<Expression>,
+    pub name: Token,
 }
 
 impl Expression {
-    pub fn accept<T>(&self, visitor: &dyn ExpressionVisitor<T>) -> Result<T, Error> {
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
         match self {
             Self::Literal(expression) => expression.accept(visitor),
             Self::Unary(expression) => expression.accept(visitor),
             Self::Binary(expression) => expression.accept(visitor),
             Self::Grouping(expression) => expression.accept(visitor),
             Self::VariableExpression(expression) => expression.accept(visitor),
+            Self::Ternary(expression) => expression.accept(visitor),
+            Self::NilCoalescing(expression) => expression.accept(visitor),
+            Self::Assignment(expression) => expression.accept(visitor),
+            Self::Update(expression) => expression.accept(visitor),
+            Self::Call(expression) => expression.accept(visitor),
+            Self::Get(expression) => expression.accept(visitor),
         }
     }
 }
 
 pub trait ExpressionVisitor<T> {
-    fn visit_literal_expression(&self, expression: &LiteralExpression) -> Result<T, Error>;
-    fn visit_unary_expression(&self, expression: &UnaryExpression) -> Result<T, Error>;
-    fn visit_binary_expression(&self, expression: &BinaryExpression) -> Result<T, Error>;
-    fn visit_grouping_expression(&self, expression: &GroupingExpression) -> Result<T, Error>;
-    fn visit_variable_expression(&self, expression: &VariableExpression) -> Result<T, Error>;
+    fn visit_literal_expression(&mut self, expression: &LiteralExpression) -> Result<T, Error>;
+    fn visit_unary_expression(&mut self, expression: &UnaryExpression) -> Result<T, Error>;
+    fn visit_binary_expression(&mut self, expression: &BinaryExpression) -> Result<T, Error>;
+    fn visit_grouping_expression(&mut self, expression: &GroupingExpression) -> Result<T, Error>;
+    fn visit_variable_expression(&mut self, expression: &VariableExpression) -> Result<T, Error>;
+    fn visit_ternary_expression(&mut self, expression: &TernaryExpression) -> Result<T, Error>;
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<T, Error>;
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<T, Error>;
+    fn visit_update_expression(&mut self, expression: &UpdateExpression) -> Result<T, Error>;
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<T, Error>;
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<T, Error>;
 }
 
 impl LiteralExpression {
@@ -58,7 +131,7 @@ impl LiteralExpression {
         Self { value }
     }
 
-    pub fn accept<T>(&self, visitor: &dyn ExpressionVisitor<T>) -> Result<T, Error> {
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
         visitor.visit_literal_expression(self)
     }
 }
@@ -71,7 +144,7 @@ impl UnaryExpression {
         }
     }
 
-    pub fn accept<T>(&self, visitor: &dyn ExpressionVisitor<T>) -> Result<T, Error> {
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
         visitor.visit_unary_expression(self)
     }
 }
@@ -85,7 +158,7 @@ impl BinaryExpression {
         }
     }
 
-    pub fn accept<T>(&self, visitor: &dyn ExpressionVisitor<T>) -> Result<T, Error> {
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
         visitor.visit_binary_expression(self)
     }
 }
@@ -97,17 +170,105 @@ impl GroupingExpression {
         }
     }
 
-    pub fn accept<T>(&self, visitor: &dyn ExpressionVisitor<T>) -> Result<T, Error> {
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
         visitor.visit_grouping_expression(self)
     }
 }
 
 impl VariableExpression {
     pub fn new(identifier: Token) -> Self {
-        Self { identifier }
+        Self {
+            identifier,
+            binding: Cell::new(Binding::Global),
+        }
     }
 
-    pub fn accept<T>(&self, visitor: &dyn ExpressionVisitor<T>) -> Result<T, Error> {
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
         visitor.visit_variable_expression(self)
     }
 }
+
+impl TernaryExpression {
+    pub fn new(conditional: Expression, then_branch: Expression, else_branch: Expression) -> Self {
+        Self {
+            conditional: Box::new(conditional),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        }
+    }
+
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
+        visitor.visit_ternary_expression(self)
+    }
+}
+
+impl NilCoalescingExpression {
+    pub fn new(left: Expression, operator: Token, right: Expression) -> Self {
+        Self {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
+        visitor.visit_nil_coalescing_expression(self)
+    }
+}
+
+impl AssignmentExpression {
+    pub fn new(identifier: Token, operator: Token, value: Expression) -> Self {
+        Self {
+            identifier,
+            operator,
+            value: Box::new(value),
+            binding: Cell::new(Binding::Global),
+        }
+    }
+
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
+        visitor.visit_assignment_expression(self)
+    }
+}
+
+impl UpdateExpression {
+    pub fn new(identifier: Token, operator: Token, prefix: bool) -> Self {
+        Self {
+            identifier,
+            operator,
+            prefix,
+            binding: Cell::new(Binding::Global),
+        }
+    }
+
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
+        visitor.visit_update_expression(self)
+    }
+}
+
+impl CallExpression {
+    pub fn new(callee: Expression, paren: Token, arguments: Vec<Expression>) -> Self {
+        Self {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }
+    }
+
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
+        visitor.visit_call_expression(self)
+    }
+}
+
+impl GetExpression {
+    pub fn new(object: Expression, name: Token) -> Self {
+        Self {
+            object: Box::new(object),
+            name,
+        }
+    }
+
+    pub fn accept<T>(&self, visitor: &mut dyn ExpressionVisitor<T>) -> Result<T, Error> {
+        visitor.visit_get_expression(self)
+    }
+}
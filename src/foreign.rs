@@ -0,0 +1,167 @@
+use std::{any::Any, fmt, rc::Rc};
+
+use crate::{
+    error::{Error, ErrorType},
+    heap,
+    interner::Symbol,
+    native::NativeFunction,
+    object::Object,
+    value::Value,
+};
+
+// A value owned by the host program, such as a database handle or a request
+// context, that scripts can pass around, print and use through its properties
+// and methods without it ever being copied into the heap.
+pub trait Foreign: Any {
+    // The name scripts see in error messages and when the value is printed.
+    fn type_name(&self) -> &str;
+
+    // How `print` shows the value, `<TypeName>` by default.
+    fn display(&self) -> Option<String> {
+        None
+    }
+
+    // The value of a property such as `ctx.path`, if the type has one.
+    fn get(&self, _property: &str) -> Option<Value> {
+        None
+    }
+
+    // Fills in the methods scripts can call, as in `ctx.header("x")`.
+    fn methods(_methods: &mut Methods<Self>)
+    where
+        Self: Sized,
+    {
+    }
+}
+
+type Method<T> = dyn Fn(&T, &[Value]) -> Result<Value, Error>;
+
+// The method table of a foreign type, filled in by `Foreign::methods`.
+pub struct Methods<T> {
+    methods: Vec<(&'static str, usize, Box<Method<T>>)>,
+}
+
+impl<T> Methods<T> {
+    pub fn add(
+        &mut self,
+        name: &'static str,
+        arity: usize,
+        method: impl Fn(&T, &[Value]) -> Result<Value, Error> + 'static,
+    ) -> &mut Self {
+        self.methods.push((name, arity, Box::new(method)));
+        self
+    }
+}
+
+struct ErasedMethod {
+    name: &'static str,
+    arity: usize,
+    function: Rc<Method<dyn Foreign>>,
+}
+
+// A host value together with the method table of its type.
+pub struct ForeignObject {
+    value: Rc<dyn Foreign>,
+    methods: Vec<ErasedMethod>,
+}
+
+impl ForeignObject {
+    pub fn new<T: Foreign>(value: T) -> Self {
+        let mut methods = Methods {
+            methods: Vec::new(),
+        };
+        T::methods(&mut methods);
+
+        // The table only ever sees values of type `T`, so the downcast holds.
+        let methods = methods
+            .methods
+            .into_iter()
+            .map(|(name, arity, method)| {
+                let function: Rc<Method<dyn Foreign>> = Rc::new(move |value, arguments| {
+                    let value = (value as &dyn Any)
+                        .downcast_ref::<T>()
+                        .expect("foreign method called on another type");
+                    method(value, arguments)
+                });
+                ErasedMethod {
+                    name,
+                    arity,
+                    function,
+                }
+            })
+            .collect();
+
+        Self {
+            value: Rc::new(value),
+            methods,
+        }
+    }
+
+    pub fn type_name(&self) -> &str {
+        self.value.type_name()
+    }
+
+    pub fn downcast_ref<T: Foreign>(&self) -> Option<&T> {
+        (&*self.value as &dyn Any).downcast_ref::<T>()
+    }
+
+    // Getters come first; a method is returned bound to this object, as a
+    // native function that can be called like any other.
+    fn property(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.value.get(name) {
+            return Some(value);
+        }
+
+        let method = self.methods.iter().find(|method| method.name == name)?;
+        let value = Rc::clone(&self.value);
+        let function = Rc::clone(&method.function);
+        Some(Value::Native(Rc::new(NativeFunction::new(
+            &format!("{}.{}", self.type_name(), name),
+            method.arity,
+            move |arguments| function(&*value, arguments),
+        ))))
+    }
+}
+
+// Two foreign values are the same value only if they wrap the same object.
+impl PartialEq for ForeignObject {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for ForeignObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.display() {
+            Some(display) => write!(f, "{}", display),
+            None => write!(f, "<{}>", self.type_name()),
+        }
+    }
+}
+
+impl fmt::Debug for ForeignObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.type_name())
+    }
+}
+
+// Reads `object.name` for every backend.
+pub(crate) fn get_property(object: Object, name: Symbol, line: usize) -> Result<Object, Error> {
+    let Object::Foreign(handle) = object else {
+        return Err(Error::new(
+            line,
+            ErrorType::RuntimeError,
+            "Only foreign objects have properties",
+        ));
+    };
+
+    let foreign = heap::foreign(handle);
+    let value = foreign.property(name.as_str()).ok_or_else(|| {
+        Error::new(
+            line,
+            ErrorType::RuntimeError,
+            &format!("Undefined property '{}' on {}", name, foreign.type_name()),
+        )
+    })?;
+    Object::try_from(value).map_err(|error| error.at_line(Some(line)))
+}
@@ -0,0 +1,308 @@
+use crate::{
+    error::Error,
+    expression::{
+        AssignmentExpression, BinaryExpression, CallExpression, Expression, ExpressionVisitor,
+        GetExpression, GroupingExpression, LiteralExpression, NilCoalescingExpression,
+        TernaryExpression, UnaryExpression, UpdateExpression, VariableExpression,
+    },
+    statement::{
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchStatement,
+        PrintStatement, ReturnStatement, Statement, StatementVisitor, VariableStatement,
+    },
+};
+
+const INDENT: &str = "    ";
+
+// Prints the statements produced by the `Parser` back as Lox source in one
+// canonical layout: four-space indents, one statement per line and a blank
+// line around every function, block, `if` and `match`. Parentheses are only
+// the ones that were written, so the output parses back into the same tree.
+// Comments never reach the parser, so they are not kept.
+pub struct Formatter {
+    output: String,
+    depth: usize,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Self {
+            output: String::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn format(mut self, statements: &[Statement]) -> Result<String, Error> {
+        self.statements(statements)?;
+        Ok(self.output)
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<(), Error> {
+        for (index, statement) in statements.iter().enumerate() {
+            let spaced =
+                index > 0 && (is_compound(&statements[index - 1]) || is_compound(statement));
+            if spaced {
+                self.output.push('\n');
+            }
+            self.line_start();
+            statement.accept(self)?;
+            self.output.push('\n');
+        }
+        Ok(())
+    }
+
+    // The braces of a block open on the line of the statement that owns it.
+    fn block(&mut self, statements: &[Statement]) -> Result<(), Error> {
+        if statements.is_empty() {
+            self.output.push_str("{}");
+            return Ok(());
+        }
+
+        self.output.push_str("{\n");
+        self.depth += 1;
+        self.statements(statements)?;
+        self.depth -= 1;
+        self.line_start();
+        self.output.push('}');
+        Ok(())
+    }
+
+    // The body of an `if`, `else` or match arm: a block stays on the same
+    // line, anything else follows a space.
+    fn branch(&mut self, statement: &Statement) -> Result<(), Error> {
+        self.output.push(' ');
+        statement.accept(self)
+    }
+
+    fn line_start(&mut self) {
+        for _ in 0..self.depth {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<String, Error> {
+        expression.accept(self)
+    }
+
+    fn list(&mut self, expressions: &[Expression]) -> Result<String, Error> {
+        let mut items = Vec::new();
+        for expression in expressions {
+            items.push(self.expression(expression)?);
+        }
+        Ok(items.join(", "))
+    }
+}
+
+fn is_compound(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::BlockStatement(_)
+            | Statement::IfStatement(_)
+            | Statement::MatchStatement(_)
+            | Statement::FunctionStatement(_)
+    )
+}
+
+impl StatementVisitor<()> for Formatter {
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) -> Result<(), Error> {
+        let expression = self.expression(&statement.expression)?;
+        self.output.push_str(&format!("{};", expression));
+        Ok(())
+    }
+
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<(), Error> {
+        let expression = self.expression(&statement.expression)?;
+        self.output.push_str(&format!("print {};", expression));
+        Ok(())
+    }
+
+    fn visit_variable_statement(&mut self, statement: &VariableStatement) -> Result<(), Error> {
+        let initializer = self.expression(&statement.initializer)?;
+        self.output.push_str(&format!(
+            "var {} = {};",
+            statement.identifier.lexeme, initializer
+        ));
+        Ok(())
+    }
+
+    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<(), Error> {
+        self.block(&statement.statements)
+    }
+
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<(), Error> {
+        let conditional = self.expression(&statement.conditional)?;
+        self.output.push_str(&format!("if ({})", conditional));
+        self.branch(&statement.then_branch)?;
+
+        if let Some(else_branch) = &statement.else_branch {
+            if matches!(*statement.then_branch, Statement::BlockStatement(_)) {
+                self.output.push(' ');
+            } else {
+                self.output.push('\n');
+                self.line_start();
+            }
+            self.output.push_str("else");
+            self.branch(else_branch)?;
+        }
+        Ok(())
+    }
+
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<(), Error> {
+        let scrutinee = self.expression(&statement.scrutinee)?;
+        self.output.push_str(&format!("match ({}) {{\n", scrutinee));
+        self.depth += 1;
+
+        for arm in &statement.arms {
+            let patterns = self.list(&arm.patterns)?;
+            self.line_start();
+            self.output.push_str(&format!("{} =>", patterns));
+            self.branch(&arm.body)?;
+            self.output.push('\n');
+        }
+        if let Some(default_branch) = &statement.default_branch {
+            self.line_start();
+            self.output.push_str("_ =>");
+            self.branch(default_branch)?;
+            self.output.push('\n');
+        }
+
+        self.depth -= 1;
+        self.line_start();
+        self.output.push('}');
+        Ok(())
+    }
+
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<(), Error> {
+        let parameters: Vec<String> = statement
+            .parameters
+            .iter()
+            .map(|parameter| parameter.lexeme.to_string())
+            .collect();
+        self.output.push_str(&format!(
+            "fun {}({}) ",
+            statement.name.lexeme,
+            parameters.join(", ")
+        ));
+        self.block(&statement.body)
+    }
+
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<(), Error> {
+        let value = self.expression(&statement.value)?;
+        self.output.push_str(&format!("return {};", value));
+        Ok(())
+    }
+}
+
+impl ExpressionVisitor<String> for Formatter {
+    fn visit_literal_expression(
+        &mut self,
+        expression: &LiteralExpression,
+    ) -> Result<String, Error> {
+        Ok(expression.value.to_source())
+    }
+
+    fn visit_unary_expression(&mut self, expression: &UnaryExpression) -> Result<String, Error> {
+        let operator = expression.operator.lexeme.to_string();
+        let right = self.expression(&expression.right)?;
+
+        // `- -x` must not turn into `--x`.
+        if right.starts_with(&operator) {
+            Ok(format!("{} {}", operator, right))
+        } else {
+            Ok(format!("{}{}", operator, right))
+        }
+    }
+
+    fn visit_binary_expression(&mut self, expression: &BinaryExpression) -> Result<String, Error> {
+        Ok(format!(
+            "{} {} {}",
+            self.expression(&expression.left)?,
+            expression.operator.lexeme,
+            self.expression(&expression.right)?
+        ))
+    }
+
+    fn visit_grouping_expression(
+        &mut self,
+        expression: &GroupingExpression,
+    ) -> Result<String, Error> {
+        Ok(format!("({})", self.expression(&expression.expressions)?))
+    }
+
+    fn visit_variable_expression(
+        &mut self,
+        expression: &VariableExpression,
+    ) -> Result<String, Error> {
+        Ok(expression.identifier.lexeme.to_string())
+    }
+
+    fn visit_ternary_expression(
+        &mut self,
+        expression: &TernaryExpression,
+    ) -> Result<String, Error> {
+        Ok(format!(
+            "{} ? {} : {}",
+            self.expression(&expression.conditional)?,
+            self.expression(&expression.then_branch)?,
+            self.expression(&expression.else_branch)?
+        ))
+    }
+
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<String, Error> {
+        Ok(format!(
+            "{} ?? {}",
+            self.expression(&expression.left)?,
+            self.expression(&expression.right)?
+        ))
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<String, Error> {
+        Ok(format!(
+            "{} {} {}",
+            expression.identifier.lexeme,
+            expression.operator.lexeme,
+            self.expression(&expression.value)?
+        ))
+    }
+
+    fn visit_update_expression(&mut self, expression: &UpdateExpression) -> Result<String, Error> {
+        if expression.prefix {
+            Ok(format!(
+                "{}{}",
+                expression.operator.lexeme, expression.identifier.lexeme
+            ))
+        } else {
+            Ok(format!(
+                "{}{}",
+                expression.identifier.lexeme, expression.operator.lexeme
+            ))
+        }
+    }
+
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<String, Error> {
+        Ok(format!(
+            "{}({})",
+            self.expression(&expression.callee)?,
+            self.list(&expression.arguments)?
+        ))
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<String, Error> {
+        Ok(format!(
+            "{}.{}",
+            self.expression(&expression.object)?,
+            expression.name.lexeme
+        ))
+    }
+}
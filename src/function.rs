@@ -0,0 +1,122 @@
+use std::{fmt, rc::Rc};
+
+use crate::{
+    chunk::CompiledFunction,
+    error::{Error, ErrorType},
+    heap::{self, Handle},
+    interner::Symbol,
+    ir,
+    statement::Statement,
+};
+
+// A function declared in a script. Each backend runs its own form of the body,
+// and `captured` holds the heap objects the function closes over: the frames
+// around its declaration, or the upvalues of the `VirtualMachine`.
+pub(crate) struct Function {
+    pub name: Symbol,
+    pub arity: usize,
+    pub code: Code,
+    pub captured: Vec<Handle>,
+}
+
+pub(crate) enum Code {
+    Statements(Rc<[Statement]>),
+    Bytecode(Rc<CompiledFunction>),
+    Ir(Rc<ir::Function>),
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name)
+    }
+}
+
+// How deeply calls may nest, the same in every backend. `Limits` only bounds
+// the nesting within one function body.
+const MAX_CALL_DEPTH: usize = 1024;
+
+pub(crate) fn check_depth(depth: usize, line: Option<usize>) -> Result<(), Error> {
+    if depth < MAX_CALL_DEPTH {
+        return Ok(());
+    }
+
+    Err(Error::new(line, ErrorType::RuntimeError, "Stack overflow"))
+}
+
+// Every kind of function checks its arguments the same way.
+pub(crate) fn check_arity(
+    arity: usize,
+    arguments: usize,
+    line: Option<usize>,
+) -> Result<(), Error> {
+    if arguments == arity {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        line,
+        ErrorType::RuntimeError,
+        &format!("Expected {} arguments but got {}", arity, arguments),
+    ))
+}
+
+// A script function as the host holds it. The function stays alive, along with
+// everything it closes over, for as long as the host keeps this around.
+pub struct LoxFunction {
+    handle: Handle,
+    name: String,
+    arity: usize,
+}
+
+impl LoxFunction {
+    pub(crate) fn new(handle: Handle) -> Self {
+        let function = heap::function(handle);
+        heap::retain(handle);
+        Self {
+            handle,
+            name: function.name.to_string(),
+            arity: function.arity,
+        }
+    }
+
+    pub(crate) fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+}
+
+impl Clone for LoxFunction {
+    fn clone(&self) -> Self {
+        heap::retain(self.handle);
+        Self {
+            handle: self.handle,
+            name: self.name.clone(),
+            arity: self.arity,
+        }
+    }
+}
+
+impl Drop for LoxFunction {
+    fn drop(&mut self) {
+        heap::release(self.handle);
+    }
+}
+
+impl PartialEq for LoxFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name)
+    }
+}
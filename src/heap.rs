@@ -0,0 +1,524 @@
+// The managed heap that owns every string payload and scope frame. Values refer
+// to heap objects through `Handle`s, and a mark-and-sweep collector frees the
+// objects that are no longer reachable from the roots of the running backend.
+//
+// There is one heap per thread. Collections only happen at safepoints, where a
+// backend hands over its roots: the values it keeps in globals, scopes, stacks
+// or temporaries. Anything a backend holds on the Rust stack across a safepoint
+// must be pushed with `push_root` first, and values that have to outlive the
+// backend's run, such as an interpreter's globals between calls, are kept with
+// `retain`.
+
+use std::{cell::RefCell, fmt, mem, rc::Rc};
+
+use crate::{
+    error::{Error, ErrorType},
+    foreign::ForeignObject,
+    function::Function,
+    native::NativeFunction,
+    object::Object,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Handle {
+    index: u32,
+    generation: u16,
+}
+
+// A handle fits in 48 bits, which leaves room to NaN-box it.
+#[cfg(feature = "nan-boxing")]
+impl Handle {
+    pub(crate) fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub(crate) fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u16,
+        }
+    }
+}
+
+enum HeapObject {
+    String(String),
+    Frame(Vec<Object>),
+    Native(Rc<NativeFunction>),
+    Foreign(Rc<ForeignObject>),
+    Function(Rc<Function>),
+    Upvalue(Upvalue),
+}
+
+// A variable a closure in the `VirtualMachine` captured: still in its stack
+// slot while the block that declared it runs, and moved here once it ends.
+#[derive(Clone, Copy)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Object),
+}
+
+struct Entry {
+    generation: u16,
+    object: Option<HeapObject>,
+    marked: bool,
+    // Pinned objects are constants from the source and are never collected.
+    pinned: bool,
+    // Retained objects are roots until they are released as often.
+    retained: u32,
+}
+
+#[derive(Clone, Copy)]
+pub struct GcConfig {
+    // Bytes to allocate before the first collection.
+    pub initial_threshold: usize,
+    // After a collection, the next one runs once the heap is this many times
+    // larger than what survived.
+    pub growth_factor: usize,
+    // Collect at the first safepoint after every allocation.
+    pub stress: bool,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self {
+            initial_threshold: 1024 * 1024,
+            growth_factor: 2,
+            stress: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct GcStats {
+    pub collections: usize,
+    pub allocated_objects: usize,
+    pub freed_objects: usize,
+    pub live_objects: usize,
+    pub live_bytes: usize,
+    pub peak_bytes: usize,
+}
+
+impl fmt::Display for GcStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "collections:       {}", self.collections)?;
+        writeln!(f, "allocated objects: {}", self.allocated_objects)?;
+        writeln!(f, "freed objects:     {}", self.freed_objects)?;
+        writeln!(f, "live objects:      {}", self.live_objects)?;
+        writeln!(f, "live bytes:        {}", self.live_bytes)?;
+        write!(f, "peak bytes:        {}", self.peak_bytes)
+    }
+}
+
+#[derive(Default)]
+struct Heap {
+    entries: Vec<Entry>,
+    free: Vec<u32>,
+    temporaries: Vec<Object>,
+    bytes_allocated: usize,
+    next_gc: usize,
+    allocated_since_gc: bool,
+    memory_limit: Option<usize>,
+    config: GcConfig,
+    stats: GcStats,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap::new());
+}
+
+impl HeapObject {
+    fn size(&self) -> usize {
+        mem::size_of::<Entry>()
+            + match self {
+                Self::String(string) => string.capacity(),
+                Self::Frame(frame) => frame.capacity() * mem::size_of::<Object>(),
+                Self::Native(_) => mem::size_of::<NativeFunction>(),
+                Self::Foreign(_) => mem::size_of::<ForeignObject>(),
+                Self::Function(function) => {
+                    mem::size_of::<Function>() + function.captured.len() * mem::size_of::<Handle>()
+                }
+                Self::Upvalue(_) => 0,
+            }
+    }
+}
+
+impl Heap {
+    fn new() -> Self {
+        let config = GcConfig::default();
+        Self {
+            next_gc: config.initial_threshold,
+            config,
+            ..Default::default()
+        }
+    }
+
+    // Every object counts against the memory limit, except constants of the
+    // program, which exist before it runs.
+    fn allocate(&mut self, object: HeapObject, pinned: bool) -> Result<Handle, Error> {
+        let size = object.size();
+        if !pinned
+            && self
+                .memory_limit
+                .is_some_and(|limit| self.bytes_allocated + size > limit)
+        {
+            return Err(Error::new(None, ErrorType::MemoryError, "Out of memory"));
+        }
+
+        self.bytes_allocated += size;
+        self.stats.allocated_objects += 1;
+        self.stats.live_objects += 1;
+        self.stats.peak_bytes = self.stats.peak_bytes.max(self.bytes_allocated);
+        self.allocated_since_gc = true;
+
+        if let Some(index) = self.free.pop() {
+            let entry = &mut self.entries[index as usize];
+            entry.object = Some(object);
+            entry.pinned = pinned;
+            entry.retained = 0;
+            Ok(Handle {
+                index,
+                generation: entry.generation,
+            })
+        } else {
+            self.entries.push(Entry {
+                generation: 0,
+                object: Some(object),
+                marked: false,
+                pinned,
+                retained: 0,
+            });
+            Ok(Handle {
+                index: self.entries.len() as u32 - 1,
+                generation: 0,
+            })
+        }
+    }
+
+    fn get(&self, handle: Handle) -> &HeapObject {
+        self.entries
+            .get(handle.index as usize)
+            .filter(|entry| entry.generation == handle.generation)
+            .and_then(|entry| entry.object.as_ref())
+            .expect("use of a collected heap object")
+    }
+
+    fn get_mut(&mut self, handle: Handle) -> &mut HeapObject {
+        self.entries
+            .get_mut(handle.index as usize)
+            .filter(|entry| entry.generation == handle.generation)
+            .and_then(|entry| entry.object.as_mut())
+            .expect("use of a collected heap object")
+    }
+
+    fn collection_due(&self) -> bool {
+        if self.config.stress {
+            self.allocated_since_gc
+        } else {
+            self.bytes_allocated >= self.next_gc
+                || self.allocated_since_gc
+                    && self
+                        .memory_limit
+                        .is_some_and(|limit| self.bytes_allocated >= limit / 2)
+        }
+    }
+
+    fn collect(&mut self, mut gray: Vec<Handle>) {
+        gray.extend(self.temporaries.iter().filter_map(Object::handle));
+        gray.extend(
+            self.entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.retained > 0)
+                .map(|(index, entry)| Handle {
+                    index: index as u32,
+                    generation: entry.generation,
+                }),
+        );
+
+        while let Some(handle) = gray.pop() {
+            let Some(entry) = self.entries.get_mut(handle.index as usize) else {
+                continue;
+            };
+            if entry.generation != handle.generation || entry.marked {
+                continue;
+            }
+            entry.marked = true;
+
+            match &entry.object {
+                Some(HeapObject::Frame(frame)) => {
+                    gray.extend(frame.iter().filter_map(Object::handle))
+                }
+                Some(HeapObject::Function(function)) => gray.extend(&function.captured),
+                Some(HeapObject::Upvalue(Upvalue::Closed(value))) => gray.extend(value.handle()),
+                _ => {}
+            }
+        }
+
+        self.sweep();
+        self.stats.collections += 1;
+        self.allocated_since_gc = false;
+        self.next_gc =
+            (self.bytes_allocated * self.config.growth_factor).max(self.config.initial_threshold);
+    }
+
+    fn sweep(&mut self) {
+        let mut live_bytes = 0;
+        let mut live_objects = 0;
+
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            let Some(object) = &entry.object else {
+                continue;
+            };
+
+            if entry.marked || entry.pinned {
+                entry.marked = false;
+                live_bytes += object.size();
+                live_objects += 1;
+            } else {
+                entry.object = None;
+                entry.generation = entry.generation.wrapping_add(1);
+                self.free.push(index as u32);
+                self.stats.freed_objects += 1;
+            }
+        }
+
+        self.bytes_allocated = live_bytes;
+        self.stats.live_objects = live_objects;
+    }
+}
+
+pub fn configure(config: GcConfig) {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.config = config;
+        heap.next_gc = config.initial_threshold;
+    })
+}
+
+pub fn stats() -> GcStats {
+    HEAP.with(|heap| {
+        let heap = heap.borrow();
+        GcStats {
+            live_bytes: heap.bytes_allocated,
+            ..heap.stats
+        }
+    })
+}
+
+// Caps the bytes the heap may hold and returns the previous cap. Garbage counts
+// against the cap until a safepoint collects it, so once half of the cap is in
+// use every safepoint after an allocation collects.
+pub fn set_memory_limit(limit: Option<usize>) -> Option<usize> {
+    HEAP.with(|heap| mem::replace(&mut heap.borrow_mut().memory_limit, limit))
+}
+
+// An allocation fails with a `MemoryError`, without a line, once it would take
+// the heap over the memory limit.
+pub fn allocate_string(string: String) -> Result<Handle, Error> {
+    HEAP.with(|heap| {
+        heap.borrow_mut()
+            .allocate(HeapObject::String(string), false)
+    })
+}
+
+// String literals are pinned: a parsed program can be run again at any time, so
+// the constants it refers to are never collected.
+pub fn constant_string(string: String) -> Handle {
+    HEAP.with(|heap| heap.borrow_mut().allocate(HeapObject::String(string), true))
+        .expect("constants are not limited")
+}
+
+// Turns an object created while running code into a constant.
+pub fn pin(handle: Handle) {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        if let Some(entry) = heap.entries.get_mut(handle.index as usize) {
+            if entry.generation == handle.generation {
+                entry.pinned = true;
+            }
+        }
+    })
+}
+
+pub fn allocate_frame() -> Result<Handle, Error> {
+    HEAP.with(|heap| {
+        heap.borrow_mut()
+            .allocate(HeapObject::Frame(Vec::new()), false)
+    })
+}
+
+pub fn allocate_native(function: Rc<NativeFunction>) -> Result<Handle, Error> {
+    HEAP.with(|heap| {
+        heap.borrow_mut()
+            .allocate(HeapObject::Native(function), false)
+    })
+}
+
+pub fn allocate_foreign(object: Rc<ForeignObject>) -> Result<Handle, Error> {
+    HEAP.with(|heap| {
+        heap.borrow_mut()
+            .allocate(HeapObject::Foreign(object), false)
+    })
+}
+
+pub(crate) fn allocate_function(function: Rc<Function>) -> Result<Handle, Error> {
+    HEAP.with(|heap| {
+        heap.borrow_mut()
+            .allocate(HeapObject::Function(function), false)
+    })
+}
+
+pub fn allocate_upvalue(slot: usize) -> Result<Handle, Error> {
+    HEAP.with(|heap| {
+        heap.borrow_mut()
+            .allocate(HeapObject::Upvalue(Upvalue::Open(slot)), false)
+    })
+}
+
+pub fn with_string<R>(handle: Handle, f: impl FnOnce(&str) -> R) -> R {
+    HEAP.with(|heap| match heap.borrow().get(handle) {
+        HeapObject::String(string) => f(string),
+        _ => panic!("heap object is not a string"),
+    })
+}
+
+// Frames are only read and written a slot at a time, so no caller code runs
+// while the heap is borrowed mutably.
+fn with_frame<R>(handle: Handle, f: impl FnOnce(&mut Vec<Object>) -> R) -> R {
+    HEAP.with(|heap| match heap.borrow_mut().get_mut(handle) {
+        HeapObject::Frame(frame) => f(frame),
+        _ => panic!("heap object is not a frame"),
+    })
+}
+
+pub fn frame_slot(handle: Handle, slot: usize) -> Option<Object> {
+    with_frame(handle, |frame| frame.get(slot).copied())
+}
+
+// Returns whether the slot exists.
+pub fn set_frame_slot(handle: Handle, slot: usize, value: Object) -> bool {
+    with_frame(handle, |frame| match frame.get_mut(slot) {
+        Some(target) => {
+            *target = value;
+            true
+        }
+        None => false,
+    })
+}
+
+// Slots are defined in order, so a new one goes at the end.
+pub fn define_frame_slot(handle: Handle, slot: usize, value: Object) {
+    with_frame(handle, |frame| {
+        if slot < frame.len() {
+            frame[slot] = value;
+        } else {
+            frame.push(value);
+        }
+    })
+}
+
+// The function is handed out rather than borrowed, since calling it may use
+// the heap again.
+pub fn native(handle: Handle) -> Rc<NativeFunction> {
+    HEAP.with(|heap| match heap.borrow().get(handle) {
+        HeapObject::Native(function) => Rc::clone(function),
+        _ => panic!("heap object is not a native function"),
+    })
+}
+
+pub fn foreign(handle: Handle) -> Rc<ForeignObject> {
+    HEAP.with(|heap| match heap.borrow().get(handle) {
+        HeapObject::Foreign(object) => Rc::clone(object),
+        _ => panic!("heap object is not a foreign object"),
+    })
+}
+
+pub(crate) fn function(handle: Handle) -> Rc<Function> {
+    HEAP.with(|heap| match heap.borrow().get(handle) {
+        HeapObject::Function(function) => Rc::clone(function),
+        _ => panic!("heap object is not a function"),
+    })
+}
+
+pub fn upvalue(handle: Handle) -> Upvalue {
+    HEAP.with(|heap| match heap.borrow().get(handle) {
+        HeapObject::Upvalue(upvalue) => *upvalue,
+        _ => panic!("heap object is not an upvalue"),
+    })
+}
+
+pub fn set_upvalue(handle: Handle, upvalue: Upvalue) {
+    HEAP.with(|heap| match heap.borrow_mut().get_mut(handle) {
+        HeapObject::Upvalue(target) => *target = upvalue,
+        _ => panic!("heap object is not an upvalue"),
+    })
+}
+
+// Keeps an object, and everything it refers to, alive across collections run
+// by any backend until it is released.
+pub fn retain(handle: Handle) {
+    HEAP.with(|heap| {
+        if let Some(entry) = heap.borrow_mut().entries.get_mut(handle.index as usize) {
+            if entry.generation == handle.generation {
+                entry.retained += 1;
+            }
+        }
+    })
+}
+
+pub fn release(handle: Handle) {
+    HEAP.with(|heap| {
+        if let Some(entry) = heap.borrow_mut().entries.get_mut(handle.index as usize) {
+            if entry.generation == handle.generation {
+                entry.retained = entry.retained.saturating_sub(1);
+            }
+        }
+    })
+}
+
+// What a backend holds on to between runs, such as its globals, kept alive so
+// that other backends on this thread can't collect it in the meantime.
+// Dropping it lets go.
+#[derive(Default)]
+pub(crate) struct Retained(Vec<Handle>);
+
+impl Retained {
+    // Retains `handles` in place of whatever was retained before.
+    pub(crate) fn replace(&mut self, handles: impl Iterator<Item = Handle>) {
+        let handles: Vec<Handle> = handles.collect();
+        for handle in &handles {
+            retain(*handle);
+        }
+        for handle in self.0.drain(..) {
+            release(handle);
+        }
+        self.0 = handles;
+    }
+}
+
+impl Drop for Retained {
+    fn drop(&mut self) {
+        for handle in self.0.drain(..) {
+            release(handle);
+        }
+    }
+}
+
+pub fn push_root(object: Object) {
+    HEAP.with(|heap| heap.borrow_mut().temporaries.push(object))
+}
+
+pub fn pop_root() {
+    HEAP.with(|heap| {
+        heap.borrow_mut().temporaries.pop();
+    })
+}
+
+pub fn collection_due() -> bool {
+    HEAP.with(|heap| heap.borrow().collection_due())
+}
+
+pub fn collect(roots: impl IntoIterator<Item = Handle>) {
+    let roots: Vec<Handle> = roots.into_iter().collect();
+    HEAP.with(|heap| heap.borrow_mut().collect(roots))
+}
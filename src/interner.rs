@@ -0,0 +1,47 @@
+use std::{cell::RefCell, collections::HashMap, fmt};
+
+// A cheap handle to an interned string. Two symbols are equal exactly when
+// their strings are, so comparing and hashing them never touches the text.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+// Interned strings live for the rest of the program, which lets `Symbol::as_str`
+// hand out `'static` references without tying symbols to an interner borrow.
+// That is only cheap for a bounded set of strings, names and punctuation, so
+// string values and literals are never interned.
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+impl Symbol {
+    pub fn intern(string: &str) -> Self {
+        INTERNER.with(|interner| {
+            let mut interner = interner.borrow_mut();
+            if let Some(symbol) = interner.symbols.get(string) {
+                return *symbol;
+            }
+
+            let string: &'static str = Box::leak(string.to_string().into_boxed_str());
+            let symbol = Symbol(interner.strings.len() as u32);
+            interner.strings.push(string);
+            interner.symbols.insert(string, symbol);
+            symbol
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        INTERNER.with(|interner| interner.borrow().strings[self.0 as usize])
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -1,302 +1,576 @@
+use std::{io::Write, rc::Rc};
+
 use crate::{
+    builtins,
+    config::InterpreterConfig,
     environment::Environment,
     error::{Error, ErrorType},
-    expression::{Expression, ExpressionVisitor, LiteralExpression},
+    expression::{
+        AssignmentExpression, BinaryExpression, Binding, CallExpression, Expression,
+        ExpressionVisitor, GetExpression, LiteralExpression, NilCoalescingExpression,
+        TernaryExpression, UpdateExpression,
+    },
+    foreign,
+    function::{self, Code, Function},
+    heap::{self, Retained},
+    interner::Symbol,
+    limits::{Limits, Meter},
+    native::{self, NativeFunction},
     object::Object,
+    operation::{self, Output},
     statement::{
-        BlockStatement, ExpressionStatement, IfStatement, PrintStatement, Statement,
-        StatementVisitor, VariableStatement,
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchStatement,
+        PrintStatement, ReturnStatement, Statement, StatementVisitor, VariableStatement,
     },
     token_type::TokenType,
+    value::Value,
 };
 
+// How a statement finished: normally, or by returning from the function it is
+// in, with the returned value.
+enum Flow {
+    Normal,
+    Return(Object),
+}
+
 pub struct Interpreter {
     environment: Environment,
+    output: Output,
+    meter: Meter,
+    // Nesting within the function body being run, and the calls around it.
+    depth: usize,
+    calls: usize,
+    // The last source line reached, for errors that have no token of their own.
+    line: Option<usize>,
+    // What the globals referred to when the last call returned.
+    retained: Retained,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {
+        Self::with_config(InterpreterConfig::default())
+    }
+
+    pub fn with_limits(limits: Limits) -> Self {
+        Self::with_config(InterpreterConfig::default().limits(limits))
+    }
+
+    pub fn with_config(config: InterpreterConfig) -> Self {
+        let mut interpreter = Self {
             environment: Environment::new(),
+            output: Output::new(config.print),
+            meter: Meter::new(config.limits),
+            depth: 0,
+            calls: 0,
+            line: None,
+            retained: Retained::default(),
+        };
+        for native in builtins::natives(&config) {
+            interpreter.define_native(native);
         }
+        interpreter
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output.redirect(output);
     }
 
     pub fn interpret(&mut self, statements: &[Statement]) -> Result<(), Error> {
+        self.run(|interpreter| interpreter.execute_all(statements).map(|_| ()))
+    }
+
+    // The result must be used before anything else runs on this thread, since
+    // a string it refers to is not rooted.
+    pub(crate) fn evaluate_expression(&mut self, expression: &Expression) -> Result<Object, Error> {
+        self.run(|interpreter| interpreter.evaluate(expression))
+    }
+
+    // Makes a host function callable from scripts as a global named `name`.
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + 'static,
+    ) {
+        self.define_native(NativeFunction::new(name, arity, function));
+    }
+
+    fn define_native(&mut self, native: NativeFunction) {
+        let name = Symbol::intern(native.name());
+        self.define_global(name, Object::from_host(Value::Native(Rc::new(native))));
+    }
+
+    // Calls a function the host got hold of, such as a handler a script
+    // defined, and returns what it returned.
+    pub fn call(&mut self, callee: &Value, arguments: &[Value]) -> Result<Value, Error> {
+        let callee = Object::from_host(callee.clone());
+        let arguments: Vec<Object> = arguments.iter().cloned().map(Object::from_host).collect();
+        let value = self.run(|interpreter| interpreter.call_object(callee, &arguments, None))?;
+        Ok(Value::from(value))
+    }
+
+    // Defines a global before a script runs, replacing any of the same name.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.define_global(Symbol::intern(name), Object::from_host(value));
+    }
+
+    pub(crate) fn global(&self, name: Symbol) -> Option<Object> {
+        self.environment.global(name)
+    }
+
+    pub(crate) fn define_global(&mut self, name: Symbol, value: Object) {
+        self.environment.define(Binding::Global, name, value);
+        self.retain_globals();
+    }
+
+    // The step budget and the timeout apply to each call separately.
+    fn run<T>(&mut self, run: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        self.depth = 0;
+        self.calls = 0;
+
+        let memory_limit = self.meter.start();
+        let result = run(self);
+        self.meter.finish(memory_limit);
+        self.output.finish(&result);
+
+        self.retain_globals();
+        result
+    }
+
+    fn retain_globals(&mut self) {
+        self.retained.replace(self.environment.roots());
+    }
+
+    // Statement boundaries are the collector's safepoints, so nothing may be
+    // held on the Rust stack across `execute`, or across a call that runs
+    // statements, without being rooted.
+    fn execute(&mut self, statement: &Statement) -> Result<Flow, Error> {
+        if heap::collection_due() {
+            heap::collect(self.environment.roots());
+        }
+        self.enter()?;
+        let result = statement.accept(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn execute_block(&mut self, statements: &[Statement]) -> Result<Flow, Error> {
+        self.environment
+            .push_frame()
+            .map_err(|error| error.at_line(self.line))?;
+        let result = self.execute_all(statements);
+        self.environment.pop_frame();
+        result
+    }
+
+    // Runs statements until one of them returns.
+    fn execute_all(&mut self, statements: &[Statement]) -> Result<Flow, Error> {
         for statement in statements {
-            self.execute(statement)?;
+            if let Flow::Return(value) = self.execute(statement)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn choose_arm<'a>(
+        &mut self,
+        statement: &'a MatchStatement,
+        value: Object,
+    ) -> Result<Option<&'a Statement>, Error> {
+        for arm in &statement.arms {
+            for pattern in &arm.patterns {
+                let pattern = self.evaluate(pattern)?;
+                if operation::is_equal(&value, &pattern) {
+                    return Ok(Some(&arm.body));
+                }
+            }
+        }
+        Ok(statement.default_branch.as_deref())
+    }
+
+    // Script functions run here; anything else is up to `native::call`.
+    fn call_object(
+        &mut self,
+        callee: Object,
+        arguments: &[Object],
+        line: Option<usize>,
+    ) -> Result<Object, Error> {
+        let Object::Function(handle) = callee else {
+            return native::call(callee, arguments, line);
+        };
+        let function = heap::function(handle);
+        let Code::Statements(body) = &function.code else {
+            return native::call(callee, arguments, line);
+        };
+        function::check_arity(function.arity, arguments.len(), line)?;
+        function::check_depth(self.calls, line)?;
+
+        // The arguments are in the new frame before the first safepoint.
+        self.environment
+            .enter_call(&function.captured)
+            .map_err(|error| error.at_line(line))?;
+        for (slot, argument) in arguments.iter().enumerate() {
+            self.environment
+                .define(Binding::Local { depth: 0, slot }, function.name, *argument);
+        }
+        // The body nests from scratch; the calls are counted on their own.
+        let depth = self.depth;
+        self.depth = 0;
+        self.calls += 1;
+        let result = self.execute_all(body);
+        self.calls -= 1;
+        self.depth = depth;
+        self.environment.exit_call();
+
+        match result? {
+            Flow::Return(value) => Ok(value),
+            Flow::Normal => Ok(Object::Nil),
         }
-        Ok(())
     }
 
-    fn execute(&mut self, statement: &Statement) -> Result<Object, Error> {
-        statement.accept(self)
+    fn evaluate(&mut self, expression: &Expression) -> Result<Object, Error> {
+        self.enter()?;
+        let result = expression.accept(self);
+        self.depth -= 1;
+        result
     }
 
-    fn execute_block(&mut self, statements: &Vec<Statement>) -> Result<Object, Error> {
-        let previous = self.environment.clone();
+    fn enter(&mut self) -> Result<(), Error> {
+        self.step()?;
 
-        for statement in statements {
-            self.execute(statement)?;
+        if let Some(max_depth) = self.meter.limits.max_depth {
+            if self.depth >= max_depth {
+                return Err(self.limit_error(&format!(
+                    "Nesting exceeds the maximum depth of {}",
+                    max_depth
+                )));
+            }
         }
-        self.environment = previous;
 
-        Ok(Object::Nil)
+        self.depth += 1;
+        Ok(())
+    }
+
+    // Every statement and expression counts as one step.
+    fn step(&mut self) -> Result<(), Error> {
+        self.meter.step(self.line)
     }
 
-    fn evaluate(&self, expression: &Expression) -> Result<Object, Error> {
-        expression.accept(self)
+    fn limit_error(&self, message: &str) -> Error {
+        Error::new(self.line, ErrorType::LimitError, message)
     }
 
-    fn is_truthy(&self, object: Object) -> bool {
-        !matches!(object, Object::Nil | Object::False)
+    // One link of a binary chain, with the value of everything to its left.
+    fn apply_binary(
+        &mut self,
+        expression: &BinaryExpression,
+        left: Object,
+    ) -> Result<Object, Error> {
+        let operator = &expression.operator;
+        self.line = Some(operator.line);
+
+        heap::push_root(left);
+        let right = self.evaluate(&expression.right);
+        heap::pop_root();
+
+        operation::binary(&operator.ttype, operator.line, left, right?)
+    }
+
+    fn coalesce(
+        &mut self,
+        expression: &NilCoalescingExpression,
+        value: Object,
+    ) -> Result<Object, Error> {
+        self.line = Some(expression.operator.line);
+        match value {
+            Object::Nil => self.evaluate(&expression.right),
+            value => Ok(value),
+        }
     }
 }
 
-impl StatementVisitor<Object> for Interpreter {
-    fn visit_expression_statement(&self, statement: &ExpressionStatement) -> Result<Object, Error> {
-        let value = self.evaluate(&statement.expression)?;
-        Ok(value)
+impl StatementVisitor<Flow> for Interpreter {
+    fn visit_expression_statement(
+        &mut self,
+        statement: &ExpressionStatement,
+    ) -> Result<Flow, Error> {
+        self.evaluate(&statement.expression)?;
+        Ok(Flow::Normal)
     }
 
-    fn visit_print_statement(&self, statement: &PrintStatement) -> Result<Object, Error> {
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<Flow, Error> {
+        self.line = Some(statement.keyword.line);
         let value = self.evaluate(&statement.expression)?;
-        println!("{}", value);
-        Ok(value)
+        self.output.print(value, statement.keyword.line)?;
+        Ok(Flow::Normal)
     }
 
-    fn visit_variable_statement(
-        &mut self,
-        expression: &VariableStatement,
-    ) -> Result<Object, Error> {
+    fn visit_variable_statement(&mut self, expression: &VariableStatement) -> Result<Flow, Error> {
+        self.line = Some(expression.identifier.line);
         let value = self.evaluate(&expression.initializer)?;
-        self.environment
-            .define(&expression.identifier.lexeme, value.clone());
-        Ok(value)
+        self.environment.define(
+            expression.binding.get(),
+            expression.identifier.lexeme,
+            value,
+        );
+        Ok(Flow::Normal)
     }
 
-    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<Object, Error> {
+    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<Flow, Error> {
         self.execute_block(&statement.statements)
     }
 
-    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<Object, Error> {
-        if self.is_truthy(self.evaluate(&statement.conditional)?) {
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<Flow, Error> {
+        let conditional = self.evaluate(&statement.conditional)?;
+        if operation::is_truthy(&conditional) {
             self.execute(&statement.then_branch)
         } else {
             if let Some(else_branch) = &statement.else_branch {
                 self.execute(else_branch)
             } else {
-                Ok(Object::Nil)
+                Ok(Flow::Normal)
             }
         }
     }
+
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<Flow, Error> {
+        let value = self.evaluate(&statement.scrutinee)?;
+
+        // A pattern can call a function, so the scrutinee stays rooted until
+        // an arm is chosen.
+        heap::push_root(value);
+        let arm = self.choose_arm(statement, value);
+        heap::pop_root();
+
+        match arm? {
+            Some(body) => self.execute(body),
+            None => Ok(Flow::Normal),
+        }
+    }
+
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<Flow, Error> {
+        let function = Function {
+            name: statement.name.lexeme,
+            arity: statement.parameters.len(),
+            code: Code::Statements(Rc::clone(&statement.body)),
+            captured: self.environment.capture(),
+        };
+        let handle = heap::allocate_function(Rc::new(function))
+            .map_err(|error| error.at_line(Some(statement.name.line)))?;
+        self.environment.define(
+            statement.binding.get(),
+            statement.name.lexeme,
+            Object::Function(handle),
+        );
+        Ok(Flow::Normal)
+    }
+
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<Flow, Error> {
+        self.line = Some(statement.keyword.line);
+        Ok(Flow::Return(self.evaluate(&statement.value)?))
+    }
 }
 
 impl ExpressionVisitor<Object> for Interpreter {
-    fn visit_literal_expression(&self, expression: &LiteralExpression) -> Result<Object, Error> {
-        Ok(expression.value.clone())
+    fn visit_literal_expression(
+        &mut self,
+        expression: &LiteralExpression,
+    ) -> Result<Object, Error> {
+        Ok(expression.value)
     }
 
     fn visit_unary_expression(
-        &self,
+        &mut self,
         expression: &crate::expression::UnaryExpression,
     ) -> Result<Object, Error> {
-        let right = self.evaluate(&expression.right)?;
-
         let operator = &expression.operator;
+        self.line = Some(operator.line);
 
-        match operator.ttype {
-            TokenType::Minus => match right {
-                Object::Number(num) => Ok(Object::Number(-num)),
-                _ => Ok(Object::Nil),
-            },
-
-            TokenType::Bang => {
-                if self.is_truthy(right) {
-                    Ok(Object::False)
-                } else {
-                    Ok(Object::True)
-                }
-            }
+        let right = self.evaluate(&expression.right)?;
 
-            _ => Err(Error::new(
-                operator.line,
-                ErrorType::RuntimeError,
-                "Operator does not support unary operation",
-            )),
-        }
+        operation::unary(&operator.ttype, operator.line, right)
     }
 
-    fn visit_binary_expression(
-        &self,
-        expression: &crate::expression::BinaryExpression,
-    ) -> Result<Object, Error> {
-        let left = self.evaluate(&expression.left)?;
-        let right = self.evaluate(&expression.right)?;
-        let operator = &expression.operator;
-
-        match operator.ttype {
-            TokenType::Plus => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left + right)),
-                (Object::String(left), Object::String(right)) => {
-                    Ok(Object::String(format!("{}{}", left, right)))
-                }
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be either number or string",
-                )),
-            },
-
-            TokenType::Minus => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left - right)),
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be a number",
-                )),
-            },
-
-            TokenType::Star => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left * right)),
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be a number",
-                )),
-            },
-
-            TokenType::Slash => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left / right)),
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be a number",
-                )),
-            },
-
-            TokenType::EqualEqual => {
-                if left == right {
-                    Ok(Object::True)
-                } else {
-                    Ok(Object::False)
-                }
-            }
+    // A chain such as `1 + 2 + 3` is a tree that leans left, one level per
+    // operator. It is evaluated from the innermost operand out, so that a long
+    // chain neither recurses nor counts as nesting. A single operator, by far
+    // the most common case, needs no list of the chain.
+    fn visit_binary_expression(&mut self, expression: &BinaryExpression) -> Result<Object, Error> {
+        if !matches!(*expression.left, Expression::Binary(_)) {
+            let left = self.evaluate(&expression.left)?;
+            return self.apply_binary(expression, left);
+        }
 
-            TokenType::BangEqual => {
-                if left != right {
-                    Ok(Object::True)
-                } else {
-                    Ok(Object::False)
-                }
-            }
+        let mut chain = vec![expression];
+        while let Expression::Binary(left) = &*chain[chain.len() - 1].left {
+            self.step()?;
+            chain.push(left);
+        }
 
-            TokenType::Greater => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => {
-                    if left > right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (Object::String(left), Object::String(right)) => {
-                    if left > right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be either number or string",
-                )),
-            },
-
-            TokenType::GreaterEqual => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => {
-                    if left >= right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (Object::String(left), Object::String(right)) => {
-                    if left >= right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be either number or string",
-                )),
-            },
-
-            TokenType::Less => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => {
-                    if left < right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (Object::String(left), Object::String(right)) => {
-                    if left < right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be either number or string",
-                )),
-            },
-
-            TokenType::LessEqual => match (left, right) {
-                (Object::Number(left), Object::Number(right)) => {
-                    if left <= right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (Object::String(left), Object::String(right)) => {
-                    if left < right {
-                        Ok(Object::True)
-                    } else {
-                        Ok(Object::False)
-                    }
-                }
-                (_, _) => Err(Error::new(
-                    operator.line,
-                    ErrorType::RuntimeError,
-                    "Operand must be either number or string",
-                )),
-            },
-
-            _ => Err(Error::new(
-                operator.line,
-                ErrorType::RuntimeError,
-                "Operator does not support binary opertaion",
-            )),
+        let mut left = self.evaluate(&chain[chain.len() - 1].left)?;
+        for expression in chain.into_iter().rev() {
+            left = self.apply_binary(expression, left)?;
         }
+        Ok(left)
     }
 
     fn visit_grouping_expression(
-        &self,
+        &mut self,
         expression: &crate::expression::GroupingExpression,
     ) -> Result<Object, Error> {
-        Ok(self.evaluate(&expression.expressions)?)
+        self.evaluate(&expression.expressions)
     }
 
     fn visit_variable_expression(
-        &self,
+        &mut self,
         expression: &crate::expression::VariableExpression,
     ) -> Result<Object, Error> {
-        self.environment.access(&expression.identifier)
+        self.line = Some(expression.identifier.line);
+        let identifier = &expression.identifier;
+        self.environment
+            .access(expression.binding.get(), identifier.lexeme, identifier.line)
+    }
+
+    fn visit_ternary_expression(
+        &mut self,
+        expression: &TernaryExpression,
+    ) -> Result<Object, Error> {
+        let conditional = self.evaluate(&expression.conditional)?;
+        if operation::is_truthy(&conditional) {
+            self.evaluate(&expression.then_branch)
+        } else {
+            self.evaluate(&expression.else_branch)
+        }
+    }
+
+    // Chains of `??` are evaluated in a loop, as chains of binary operators are.
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<Object, Error> {
+        if !matches!(*expression.left, Expression::NilCoalescing(_)) {
+            let value = self.evaluate(&expression.left)?;
+            return self.coalesce(expression, value);
+        }
+
+        let mut chain = vec![expression];
+        while let Expression::NilCoalescing(left) = &*chain[chain.len() - 1].left {
+            self.step()?;
+            chain.push(left);
+        }
+
+        let mut value = self.evaluate(&chain[chain.len() - 1].left)?;
+        for expression in chain.into_iter().rev() {
+            value = self.coalesce(expression, value)?;
+        }
+        Ok(value)
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<Object, Error> {
+        let operator = &expression.operator;
+        self.line = Some(operator.line);
+        let value = match operator.ttype {
+            TokenType::Equal => self.evaluate(&expression.value)?,
+            _ => {
+                let current = self.environment.access(
+                    expression.binding.get(),
+                    expression.identifier.lexeme,
+                    expression.identifier.line,
+                )?;
+                heap::push_root(current);
+                let value = self.evaluate(&expression.value);
+                heap::pop_root();
+                let value = value?;
+                let binary_operator = match operator.ttype {
+                    TokenType::PlusEqual => TokenType::Plus,
+                    TokenType::MinusEqual => TokenType::Minus,
+                    TokenType::StarEqual => TokenType::Star,
+                    _ => TokenType::Slash,
+                };
+                operation::binary(&binary_operator, operator.line, current, value)?
+            }
+        };
+
+        self.environment.assign(
+            expression.binding.get(),
+            expression.identifier.lexeme,
+            expression.identifier.line,
+            value,
+        )?;
+        Ok(value)
+    }
+
+    fn visit_update_expression(&mut self, expression: &UpdateExpression) -> Result<Object, Error> {
+        let operator = &expression.operator;
+        self.line = Some(operator.line);
+        let identifier = &expression.identifier;
+        let current = self.environment.access(
+            expression.binding.get(),
+            identifier.lexeme,
+            identifier.line,
+        )?;
+        let binary_operator = match operator.ttype {
+            TokenType::PlusPlus => TokenType::Plus,
+            _ => TokenType::Minus,
+        };
+        let value = operation::binary(
+            &binary_operator,
+            operator.line,
+            current,
+            Object::Number(1.0),
+        )?;
+
+        self.environment.assign(
+            expression.binding.get(),
+            identifier.lexeme,
+            identifier.line,
+            value,
+        )?;
+        if expression.prefix {
+            Ok(value)
+        } else {
+            Ok(current)
+        }
+    }
+
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<Object, Error> {
+        let line = expression.paren.line;
+        self.line = Some(line);
+
+        // A call in a later argument runs statements, so the callee and the
+        // arguments evaluated so far stay rooted until this call is made.
+        let callee = self.evaluate(&expression.callee)?;
+        heap::push_root(callee);
+        let mut arguments = Vec::with_capacity(expression.arguments.len());
+        let evaluated = expression.arguments.iter().try_for_each(|argument| {
+            let value = self.evaluate(argument)?;
+            heap::push_root(value);
+            arguments.push(value);
+            Ok(())
+        });
+        for _ in 0..=arguments.len() {
+            heap::pop_root();
+        }
+        evaluated?;
+
+        self.call_object(callee, &arguments, Some(line))
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<Object, Error> {
+        let line = expression.name.line;
+        self.line = Some(line);
+
+        let object = self.evaluate(&expression.object)?;
+        foreign::get_property(object, expression.name.lexeme, line)
     }
 }
@@ -0,0 +1,251 @@
+use std::{fmt, rc::Rc};
+
+use crate::{expression::Binding, interner::Symbol, object::Object, token_type::TokenType};
+
+pub type Temporary = usize;
+pub type BlockId = usize;
+
+pub enum Instruction {
+    Constant {
+        target: Temporary,
+        value: Object,
+    },
+    Copy {
+        target: Temporary,
+        source: Temporary,
+    },
+    Unary {
+        target: Temporary,
+        operator: TokenType,
+        operand: Temporary,
+        line: usize,
+    },
+    Binary {
+        target: Temporary,
+        operator: TokenType,
+        left: Temporary,
+        right: Temporary,
+        line: usize,
+    },
+    // Variables are found through the binding the `Resolver` gave them; the
+    // name is for globals and for reading the program.
+    Load {
+        target: Temporary,
+        name: Symbol,
+        binding: Binding,
+        line: usize,
+    },
+    Store {
+        name: Symbol,
+        binding: Binding,
+        source: Temporary,
+        line: usize,
+    },
+    Define {
+        name: Symbol,
+        binding: Binding,
+        source: Temporary,
+    },
+    Print {
+        source: Temporary,
+        line: usize,
+    },
+    Call {
+        target: Temporary,
+        callee: Temporary,
+        arguments: Vec<Temporary>,
+        line: usize,
+    },
+    GetProperty {
+        target: Temporary,
+        object: Temporary,
+        name: Symbol,
+        line: usize,
+    },
+    Closure {
+        target: Temporary,
+        function: Rc<Function>,
+    },
+    EnterScope,
+    ExitScope,
+}
+
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Temporary,
+        then_block: BlockId,
+        else_block: BlockId,
+    },
+    Return(Option<Temporary>),
+}
+
+pub struct BasicBlock {
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+// A lowered program: basic blocks addressed by index, starting at block 0, and
+// the number of temporaries the blocks refer to.
+pub struct Program {
+    pub blocks: Vec<BasicBlock>,
+    pub temporaries: usize,
+}
+
+// A function declaration, lowered on its own. A call runs `body` with fresh
+// temporaries, the arguments in the first slots of a new frame and whatever
+// its `Return` terminator gives back as the result.
+pub struct Function {
+    pub name: Symbol,
+    pub parameters: Vec<Symbol>,
+    pub body: Program,
+}
+
+fn unary_mnemonic(operator: &TokenType) -> &'static str {
+    match operator {
+        TokenType::Minus => "neg",
+        TokenType::Bang => "not",
+        TokenType::Tilde => "bitnot",
+        _ => "unknown",
+    }
+}
+
+fn binary_mnemonic(operator: &TokenType) -> &'static str {
+    match operator {
+        TokenType::Plus => "add",
+        TokenType::Minus => "sub",
+        TokenType::Star => "mul",
+        TokenType::Slash => "div",
+        TokenType::Percent => "rem",
+        TokenType::StarStar => "pow",
+        TokenType::TildeSlash => "floordiv",
+        TokenType::Ampersand => "and",
+        TokenType::Pipe => "or",
+        TokenType::Caret => "xor",
+        TokenType::LessLess => "shl",
+        TokenType::GreaterGreater => "shr",
+        TokenType::EqualEqual => "eq",
+        TokenType::BangEqual => "ne",
+        TokenType::Greater => "gt",
+        TokenType::GreaterEqual => "ge",
+        TokenType::Less => "lt",
+        TokenType::LessEqual => "le",
+        _ => "unknown",
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Constant { target, value } => {
+                write!(f, "t{} = const {}", target, value.to_source())
+            }
+            Self::Copy { target, source } => write!(f, "t{} = copy t{}", target, source),
+            Self::Unary {
+                target,
+                operator,
+                operand,
+                ..
+            } => write!(f, "t{} = {} t{}", target, unary_mnemonic(operator), operand),
+            Self::Binary {
+                target,
+                operator,
+                left,
+                right,
+                ..
+            } => write!(
+                f,
+                "t{} = {} t{}, t{}",
+                target,
+                binary_mnemonic(operator),
+                left,
+                right
+            ),
+            Self::Load { target, name, .. } => write!(f, "t{} = load {}", target, name),
+            Self::Store { name, source, .. } => write!(f, "store {}, t{}", name, source),
+            Self::Define { name, source, .. } => write!(f, "define {}, t{}", name, source),
+            Self::Print { source, .. } => write!(f, "print t{}", source),
+            Self::Call {
+                target,
+                callee,
+                arguments,
+                ..
+            } => {
+                write!(f, "t{} = call t{}(", target, callee)?;
+                for (index, argument) in arguments.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "t{}", argument)?;
+                }
+                write!(f, ")")
+            }
+            Self::Closure { target, function } => {
+                write!(f, "t{} = closure {}", target, function.name)
+            }
+            Self::GetProperty {
+                target,
+                object,
+                name,
+                ..
+            } => write!(f, "t{} = get t{}.{}", target, object, name),
+            Self::EnterScope => write!(f, "enter_scope"),
+            Self::ExitScope => write!(f, "exit_scope"),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jump(block) => write!(f, "jump bb{}", block),
+            Self::Branch {
+                condition,
+                then_block,
+                else_block,
+            } => write!(
+                f,
+                "branch t{}, bb{}, bb{}",
+                condition, then_block, else_block
+            ),
+            Self::Return(None) => write!(f, "return"),
+            Self::Return(Some(value)) => write!(f, "return t{}", value),
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (id, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "bb{}:", id)?;
+            for instruction in &block.instructions {
+                writeln!(f, "    {}", instruction)?;
+            }
+            writeln!(f, "    {}", block.terminator)?;
+        }
+
+        // The functions declared in this program follow it.
+        for block in &self.blocks {
+            for instruction in &block.instructions {
+                if let Instruction::Closure { function, .. } = instruction {
+                    write!(f, "\n{}", function)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fn {}(", self.name)?;
+        for (index, parameter) in self.parameters.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", parameter)?;
+        }
+        writeln!(f, "):")?;
+        write!(f, "{}", self.body)
+    }
+}
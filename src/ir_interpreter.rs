@@ -0,0 +1,256 @@
+use std::{io::Write, mem, rc::Rc};
+
+use crate::{
+    builtins,
+    config::InterpreterConfig,
+    environment::Environment,
+    error::Error,
+    expression::Binding,
+    foreign,
+    function::{self, Code, Function},
+    heap::{self, Retained},
+    interner::Symbol,
+    ir::{Instruction, Program, Terminator},
+    limits::Meter,
+    native::{self, NativeFunction},
+    object::Object,
+    operation::{self, Output},
+    value::Value,
+};
+
+pub struct IrInterpreter {
+    environment: Environment,
+    // The temporaries of every caller of the function that is running.
+    suspended: Vec<Vec<Object>>,
+    output: Output,
+    meter: Meter,
+    // As in `Interpreter`, what the globals referred to after the last run.
+    retained: Retained,
+}
+
+impl Default for IrInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IrInterpreter {
+    pub fn new() -> Self {
+        Self::with_config(InterpreterConfig::default())
+    }
+
+    // The same configuration an `Interpreter` takes, with the same builtins.
+    pub fn with_config(config: InterpreterConfig) -> Self {
+        let mut interpreter = Self {
+            environment: Environment::new(),
+            suspended: Vec::new(),
+            output: Output::new(config.print),
+            meter: Meter::new(config.limits),
+            retained: Retained::default(),
+        };
+        for native in builtins::natives(&config) {
+            interpreter.define_native(native);
+        }
+        interpreter
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output.redirect(output);
+    }
+
+    // As `Interpreter::set_global`.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.define_global(Symbol::intern(name), Object::from_host(value));
+    }
+
+    fn define_native(&mut self, native: NativeFunction) {
+        let name = Symbol::intern(native.name());
+        self.define_global(name, Object::from_host(Value::Native(Rc::new(native))));
+    }
+
+    fn define_global(&mut self, name: Symbol, value: Object) {
+        self.environment.define(Binding::Global, name, value);
+        self.retain_globals();
+    }
+
+    pub fn interpret(&mut self, program: &Program) -> Result<(), Error> {
+        let memory_limit = self.meter.start();
+        let result = self.run(program).map(|_| ());
+        self.meter.finish(memory_limit);
+        self.output.finish(&result);
+
+        self.retain_globals();
+        result
+    }
+
+    fn retain_globals(&mut self) {
+        self.retained.replace(self.environment.roots());
+    }
+
+    fn run(&mut self, program: &Program) -> Result<Object, Error> {
+        let mut temporaries = vec![Object::Nil; program.temporaries];
+        let mut block = 0;
+
+        loop {
+            for instruction in &program.blocks[block].instructions {
+                // Instruction boundaries are safepoints: every live value is in
+                // a temporary, a caller's temporary or the environment.
+                if heap::collection_due() {
+                    let suspended = self.suspended.iter().flatten();
+                    heap::collect(
+                        temporaries
+                            .iter()
+                            .chain(suspended)
+                            .filter_map(Object::handle)
+                            .chain(self.environment.roots()),
+                    );
+                }
+                self.meter.step(None)?;
+                self.execute(instruction, &mut temporaries)?;
+            }
+
+            match &program.blocks[block].terminator {
+                Terminator::Jump(target) => block = *target,
+                Terminator::Branch {
+                    condition,
+                    then_block,
+                    else_block,
+                } => {
+                    block = if operation::is_truthy(&temporaries[*condition]) {
+                        *then_block
+                    } else {
+                        *else_block
+                    }
+                }
+                Terminator::Return(value) => {
+                    return Ok(value.map_or(Object::Nil, |value| temporaries[value]))
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, callee: Object, arguments: &[Object], line: usize) -> Result<Object, Error> {
+        let Object::Function(handle) = callee else {
+            return native::call(callee, arguments, Some(line));
+        };
+        let function: Rc<Function> = heap::function(handle);
+        let Code::Ir(code) = &function.code else {
+            return native::call(callee, arguments, Some(line));
+        };
+        function::check_arity(function.arity, arguments.len(), Some(line))?;
+        // The caller's temporaries are suspended already, one set per call.
+        function::check_depth(self.suspended.len() - 1, Some(line))?;
+
+        self.environment
+            .enter_call(&function.captured)
+            .map_err(|error| error.at_line(Some(line)))?;
+        for (slot, argument) in arguments.iter().enumerate() {
+            self.environment
+                .define(Binding::Local { depth: 0, slot }, function.name, *argument);
+        }
+        let result = self.run(&code.body);
+        self.environment.exit_call();
+        result
+    }
+
+    fn execute(
+        &mut self,
+        instruction: &Instruction,
+        temporaries: &mut Vec<Object>,
+    ) -> Result<(), Error> {
+        match instruction {
+            Instruction::Constant { target, value } => temporaries[*target] = *value,
+
+            Instruction::Copy { target, source } => temporaries[*target] = temporaries[*source],
+
+            Instruction::Unary {
+                target,
+                operator,
+                operand,
+                line,
+            } => temporaries[*target] = operation::unary(operator, *line, temporaries[*operand])?,
+
+            Instruction::Binary {
+                target,
+                operator,
+                left,
+                right,
+                line,
+            } => {
+                temporaries[*target] =
+                    operation::binary(operator, *line, temporaries[*left], temporaries[*right])?
+            }
+
+            Instruction::Load {
+                target,
+                name,
+                binding,
+                line,
+            } => temporaries[*target] = self.environment.access(*binding, *name, *line)?,
+
+            Instruction::Store {
+                name,
+                binding,
+                source,
+                line,
+            } => self
+                .environment
+                .assign(*binding, *name, *line, temporaries[*source])?,
+
+            Instruction::Define {
+                name,
+                binding,
+                source,
+            } => self
+                .environment
+                .define(*binding, *name, temporaries[*source]),
+
+            Instruction::Print { source, line } => {
+                self.output.print(temporaries[*source], *line)?
+            }
+
+            Instruction::Call {
+                target,
+                callee,
+                arguments,
+                line,
+            } => {
+                let callee = temporaries[*callee];
+                let arguments: Vec<Object> = arguments
+                    .iter()
+                    .map(|argument| temporaries[*argument])
+                    .collect();
+
+                // The caller's temporaries stay roots while the call runs.
+                self.suspended.push(mem::take(temporaries));
+                let result = self.call(callee, &arguments, *line);
+                *temporaries = self.suspended.pop().expect("call returned twice");
+                temporaries[*target] = result?;
+            }
+
+            Instruction::Closure { target, function } => {
+                let function = Function {
+                    name: function.name,
+                    arity: function.parameters.len(),
+                    code: Code::Ir(Rc::clone(function)),
+                    captured: self.environment.capture(),
+                };
+                temporaries[*target] =
+                    Object::Function(heap::allocate_function(Rc::new(function))?);
+            }
+
+            Instruction::GetProperty {
+                target,
+                object,
+                name,
+                line,
+            } => temporaries[*target] = foreign::get_property(temporaries[*object], *name, *line)?,
+
+            Instruction::EnterScope => self.environment.push_frame()?,
+
+            Instruction::ExitScope => self.environment.pop_frame(),
+        }
+
+        Ok(())
+    }
+}
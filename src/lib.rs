@@ -1,3 +1,6 @@
+mod interner;
+#[cfg(feature = "nan-boxing")]
+mod nan_box;
 mod object;
 mod token;
 mod token_type;
@@ -5,11 +8,36 @@ mod token_type;
 mod expression;
 mod statement;
 
+mod builtins;
 mod environment;
+mod operation;
+mod resolver;
 
+mod lox;
+mod value;
+
+pub mod foreign;
+pub mod function;
+pub mod native;
+
+pub use lox::Lox;
+pub use statement::Statement;
+pub use value::{FromArguments, Value};
+
+pub mod ast_printer;
+pub mod chunk;
+pub mod compiler;
+pub mod config;
 pub mod error;
+pub mod formatter;
+pub mod heap;
 pub mod interpreter;
+pub mod ir;
+pub mod ir_interpreter;
+pub mod limits;
+pub mod lowering;
+pub mod optimizer;
 pub mod parser;
 pub mod scanner;
-
-// pub mod ast_printer;
+pub mod serializer;
+pub mod vm;
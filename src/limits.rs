@@ -0,0 +1,100 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    error::{Error, ErrorType},
+    heap,
+};
+
+// Checking the clock is comparatively slow, so the deadline is only looked at
+// once every this many steps.
+const DEADLINE_INTERVAL: u64 = 1024;
+
+// Bounds on the work a program may do, so that a runaway or hostile script is
+// stopped with a `LimitError` instead of hanging or overflowing the Rust stack.
+#[derive(Clone, Copy)]
+pub struct Limits {
+    // Steps a backend may take in one run: statements and expressions in the
+    // `Interpreter`, instructions in the virtual machine and IR interpreter.
+    pub max_steps: Option<u64>,
+    // How deeply statements and expressions may nest, both in the source the
+    // `Parser` accepts and while the `Interpreter` evaluates one function body;
+    // the other backends run code flattened and don't nest. Calls are bounded
+    // on their own, the same in every backend.
+    pub max_depth: Option<usize>,
+    // Wall-clock time one run may take.
+    pub timeout: Option<Duration>,
+    // Bytes of heap objects, such as strings, closures, foreign objects and
+    // scope frames, that may be allocated during a run.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_steps: None,
+            max_depth: Some(256),
+            timeout: None,
+            max_memory: None,
+        }
+    }
+}
+
+// Holds one run of a backend to its `Limits`, whatever the backend counts as a
+// step.
+pub(crate) struct Meter {
+    pub(crate) limits: Limits,
+    steps: u64,
+    deadline: Option<Instant>,
+}
+
+impl Meter {
+    pub(crate) fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            steps: 0,
+            deadline: None,
+        }
+    }
+
+    // Restarts the count and the clock. The heap is shared by everything on
+    // this thread, so the memory limit only holds until `finish` puts back the
+    // one this returns.
+    pub(crate) fn start(&mut self) -> Option<usize> {
+        self.steps = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+        heap::set_memory_limit(self.limits.max_memory)
+    }
+
+    pub(crate) fn finish(&mut self, memory_limit: Option<usize>) {
+        heap::set_memory_limit(memory_limit);
+    }
+
+    pub(crate) fn step(&mut self, line: Option<usize>) -> Result<(), Error> {
+        self.steps += 1;
+
+        if let Some(max_steps) = self.limits.max_steps {
+            if self.steps > max_steps {
+                return Err(Error::new(
+                    line,
+                    ErrorType::LimitError,
+                    &format!("Execution exceeds the limit of {} steps", max_steps),
+                ));
+            }
+        }
+
+        if let Some(deadline) = self.deadline {
+            if self.steps.is_multiple_of(DEADLINE_INTERVAL) && Instant::now() >= deadline {
+                return Err(Error::new(
+                    line,
+                    ErrorType::LimitError,
+                    &format!(
+                        "Execution exceeds the time limit of {} ms",
+                        self.limits.timeout.unwrap_or_default().as_millis()
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
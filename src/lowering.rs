@@ -0,0 +1,459 @@
+use std::rc::Rc;
+
+use crate::{
+    error::Error,
+    expression::{
+        AssignmentExpression, BinaryExpression, CallExpression, ExpressionVisitor, GetExpression,
+        GroupingExpression, LiteralExpression, NilCoalescingExpression, TernaryExpression,
+        UnaryExpression, UpdateExpression, VariableExpression,
+    },
+    ir::{self, BasicBlock, BlockId, Instruction, Program, Temporary, Terminator},
+    object::Object,
+    statement::{
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchStatement,
+        PrintStatement, ReturnStatement, Statement, StatementVisitor, VariableStatement,
+    },
+    token_type::TokenType,
+};
+
+struct PendingBlock {
+    instructions: Vec<Instruction>,
+    terminator: Option<Terminator>,
+}
+
+// Lowers the statements produced by the `Parser` into a `Program` of basic
+// blocks. Every expression result lands in a fresh temporary and control flow
+// becomes explicit jumps and branches between blocks.
+pub struct Lowerer {
+    blocks: Vec<PendingBlock>,
+    current: BlockId,
+    temporaries: usize,
+}
+
+impl Default for Lowerer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lowerer {
+    pub fn new() -> Self {
+        Self {
+            blocks: vec![PendingBlock {
+                instructions: Vec::new(),
+                terminator: None,
+            }],
+            current: 0,
+            temporaries: 0,
+        }
+    }
+
+    pub fn lower(mut self, statements: &[Statement]) -> Result<Program, Error> {
+        for statement in statements {
+            statement.accept(&mut self)?;
+        }
+        self.terminate(Terminator::Return(None));
+
+        Ok(Program {
+            blocks: self
+                .blocks
+                .into_iter()
+                .map(|block| BasicBlock {
+                    instructions: block.instructions,
+                    terminator: block.terminator.unwrap_or(Terminator::Return(None)),
+                })
+                .collect(),
+            temporaries: self.temporaries,
+        })
+    }
+
+    fn new_temporary(&mut self) -> Temporary {
+        self.temporaries += 1;
+        self.temporaries - 1
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PendingBlock {
+            instructions: Vec::new(),
+            terminator: None,
+        });
+        self.blocks.len() - 1
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.blocks[self.current].instructions.push(instruction);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = &mut self.blocks[self.current];
+        if block.terminator.is_none() {
+            block.terminator = Some(terminator);
+        }
+    }
+
+    fn constant(&mut self, value: Object) -> Temporary {
+        let target = self.new_temporary();
+        self.emit(Instruction::Constant { target, value });
+        target
+    }
+
+    fn binary(
+        &mut self,
+        operator: TokenType,
+        left: Temporary,
+        right: Temporary,
+        line: usize,
+    ) -> Temporary {
+        let target = self.new_temporary();
+        self.emit(Instruction::Binary {
+            target,
+            operator,
+            left,
+            right,
+            line,
+        });
+        target
+    }
+}
+
+impl StatementVisitor<()> for Lowerer {
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) -> Result<(), Error> {
+        statement.expression.accept(self)?;
+        Ok(())
+    }
+
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<(), Error> {
+        let source = statement.expression.accept(self)?;
+        self.emit(Instruction::Print {
+            source,
+            line: statement.keyword.line,
+        });
+        Ok(())
+    }
+
+    fn visit_variable_statement(&mut self, statement: &VariableStatement) -> Result<(), Error> {
+        let source = statement.initializer.accept(self)?;
+        self.emit(Instruction::Define {
+            name: statement.identifier.lexeme,
+            binding: statement.binding.get(),
+            source,
+        });
+        Ok(())
+    }
+
+    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<(), Error> {
+        self.emit(Instruction::EnterScope);
+        for statement in &statement.statements {
+            statement.accept(self)?;
+        }
+        self.emit(Instruction::ExitScope);
+        Ok(())
+    }
+
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<(), Error> {
+        let condition = statement.conditional.accept(self)?;
+        let then_block = self.new_block();
+        let else_block = self.new_block();
+        let end_block = self.new_block();
+        self.terminate(Terminator::Branch {
+            condition,
+            then_block,
+            else_block,
+        });
+
+        self.switch_to(then_block);
+        statement.then_branch.accept(self)?;
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(else_block);
+        if let Some(else_branch) = &statement.else_branch {
+            else_branch.accept(self)?;
+        }
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(end_block);
+        Ok(())
+    }
+
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<(), Error> {
+        let scrutinee = statement.scrutinee.accept(self)?;
+        let end_block = self.new_block();
+
+        for arm in &statement.arms {
+            let body_block = self.new_block();
+            for pattern in &arm.patterns {
+                let pattern = pattern.accept(self)?;
+                // Equality never fails, so there is no line to report.
+                let condition = self.binary(TokenType::EqualEqual, scrutinee, pattern, 0);
+                let next_block = self.new_block();
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then_block: body_block,
+                    else_block: next_block,
+                });
+                self.switch_to(next_block);
+            }
+            let next_arm = self.current;
+
+            self.switch_to(body_block);
+            arm.body.accept(self)?;
+            self.terminate(Terminator::Jump(end_block));
+
+            self.switch_to(next_arm);
+        }
+
+        if let Some(default_branch) = &statement.default_branch {
+            default_branch.accept(self)?;
+        }
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(end_block);
+        Ok(())
+    }
+
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<(), Error> {
+        let function = ir::Function {
+            name: statement.name.lexeme,
+            parameters: statement
+                .parameters
+                .iter()
+                .map(|parameter| parameter.lexeme)
+                .collect(),
+            body: Lowerer::new().lower(&statement.body)?,
+        };
+
+        let target = self.new_temporary();
+        self.emit(Instruction::Closure {
+            target,
+            function: Rc::new(function),
+        });
+        self.emit(Instruction::Define {
+            name: statement.name.lexeme,
+            binding: statement.binding.get(),
+            source: target,
+        });
+        Ok(())
+    }
+
+    // Anything after a `return` is unreachable and goes to a block of its own.
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<(), Error> {
+        let value = statement.value.accept(self)?;
+        self.terminate(Terminator::Return(Some(value)));
+
+        let unreachable = self.new_block();
+        self.switch_to(unreachable);
+        Ok(())
+    }
+}
+
+impl ExpressionVisitor<Temporary> for Lowerer {
+    fn visit_literal_expression(
+        &mut self,
+        expression: &LiteralExpression,
+    ) -> Result<Temporary, Error> {
+        Ok(self.constant(expression.value))
+    }
+
+    fn visit_unary_expression(&mut self, expression: &UnaryExpression) -> Result<Temporary, Error> {
+        let operand = expression.right.accept(self)?;
+        let target = self.new_temporary();
+        self.emit(Instruction::Unary {
+            target,
+            operator: expression.operator.ttype.clone(),
+            operand,
+            line: expression.operator.line,
+        });
+        Ok(target)
+    }
+
+    fn visit_binary_expression(
+        &mut self,
+        expression: &BinaryExpression,
+    ) -> Result<Temporary, Error> {
+        let left = expression.left.accept(self)?;
+        let right = expression.right.accept(self)?;
+        let operator = &expression.operator;
+        Ok(self.binary(operator.ttype.clone(), left, right, operator.line))
+    }
+
+    fn visit_grouping_expression(
+        &mut self,
+        expression: &GroupingExpression,
+    ) -> Result<Temporary, Error> {
+        expression.expressions.accept(self)
+    }
+
+    fn visit_variable_expression(
+        &mut self,
+        expression: &VariableExpression,
+    ) -> Result<Temporary, Error> {
+        let target = self.new_temporary();
+        self.emit(Instruction::Load {
+            target,
+            name: expression.identifier.lexeme,
+            binding: expression.binding.get(),
+            line: expression.identifier.line,
+        });
+        Ok(target)
+    }
+
+    fn visit_ternary_expression(
+        &mut self,
+        expression: &TernaryExpression,
+    ) -> Result<Temporary, Error> {
+        let condition = expression.conditional.accept(self)?;
+        let target = self.new_temporary();
+        let then_block = self.new_block();
+        let else_block = self.new_block();
+        let end_block = self.new_block();
+        self.terminate(Terminator::Branch {
+            condition,
+            then_block,
+            else_block,
+        });
+
+        self.switch_to(then_block);
+        let source = expression.then_branch.accept(self)?;
+        self.emit(Instruction::Copy { target, source });
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(else_block);
+        let source = expression.else_branch.accept(self)?;
+        self.emit(Instruction::Copy { target, source });
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(end_block);
+        Ok(target)
+    }
+
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<Temporary, Error> {
+        let source = expression.left.accept(self)?;
+        let target = self.new_temporary();
+        self.emit(Instruction::Copy { target, source });
+
+        let nil = self.constant(Object::Nil);
+        let line = expression.operator.line;
+        let condition = self.binary(TokenType::EqualEqual, target, nil, line);
+        let right_block = self.new_block();
+        let end_block = self.new_block();
+        self.terminate(Terminator::Branch {
+            condition,
+            then_block: right_block,
+            else_block: end_block,
+        });
+
+        self.switch_to(right_block);
+        let source = expression.right.accept(self)?;
+        self.emit(Instruction::Copy { target, source });
+        self.terminate(Terminator::Jump(end_block));
+
+        self.switch_to(end_block);
+        Ok(target)
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<Temporary, Error> {
+        let identifier = &expression.identifier;
+        let operator = &expression.operator;
+
+        let source = if operator.ttype == TokenType::Equal {
+            expression.value.accept(self)?
+        } else {
+            let current = self.new_temporary();
+            self.emit(Instruction::Load {
+                target: current,
+                name: identifier.lexeme,
+                binding: expression.binding.get(),
+                line: identifier.line,
+            });
+            let value = expression.value.accept(self)?;
+            let binary_operator = match operator.ttype {
+                TokenType::PlusEqual => TokenType::Plus,
+                TokenType::MinusEqual => TokenType::Minus,
+                TokenType::StarEqual => TokenType::Star,
+                _ => TokenType::Slash,
+            };
+            self.binary(binary_operator, current, value, operator.line)
+        };
+
+        self.emit(Instruction::Store {
+            name: identifier.lexeme,
+            binding: expression.binding.get(),
+            source,
+            line: identifier.line,
+        });
+        Ok(source)
+    }
+
+    fn visit_update_expression(
+        &mut self,
+        expression: &UpdateExpression,
+    ) -> Result<Temporary, Error> {
+        let identifier = &expression.identifier;
+        let operator = &expression.operator;
+
+        let current = self.new_temporary();
+        self.emit(Instruction::Load {
+            target: current,
+            name: identifier.lexeme,
+            binding: expression.binding.get(),
+            line: identifier.line,
+        });
+        let one = self.constant(Object::Number(1.0));
+        let binary_operator = match operator.ttype {
+            TokenType::PlusPlus => TokenType::Plus,
+            _ => TokenType::Minus,
+        };
+        let updated = self.binary(binary_operator, current, one, operator.line);
+        self.emit(Instruction::Store {
+            name: identifier.lexeme,
+            binding: expression.binding.get(),
+            source: updated,
+            line: identifier.line,
+        });
+
+        if expression.prefix {
+            Ok(updated)
+        } else {
+            Ok(current)
+        }
+    }
+
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<Temporary, Error> {
+        let callee = expression.callee.accept(self)?;
+        let mut arguments = Vec::new();
+        for argument in &expression.arguments {
+            arguments.push(argument.accept(self)?);
+        }
+
+        let target = self.new_temporary();
+        self.emit(Instruction::Call {
+            target,
+            callee,
+            arguments,
+            line: expression.paren.line,
+        });
+        Ok(target)
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<Temporary, Error> {
+        let object = expression.object.accept(self)?;
+        let target = self.new_temporary();
+        self.emit(Instruction::GetProperty {
+            target,
+            object,
+            name: expression.name.lexeme,
+            line: expression.name.line,
+        });
+        Ok(target)
+    }
+}
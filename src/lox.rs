@@ -0,0 +1,102 @@
+use std::io::{self, Write};
+
+use crate::{
+    config::InterpreterConfig,
+    error::{Error, ErrorType},
+    interner::Symbol,
+    interpreter::Interpreter,
+    limits::Limits,
+    parser::Parser,
+    scanner::Scanner,
+    value::Value,
+};
+
+// Runs Lox inside a host program. Every call goes to the same interpreter, so
+// the globals one script defines are there for the next script, for
+// `eval_expr` and for the host.
+pub struct Lox {
+    interpreter: Interpreter,
+    // The nesting limit applies to parsing as well.
+    limits: Limits,
+    // Where parser warnings go, stderr unless the host redirects them.
+    diagnostics: Box<dyn Write>,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self::with_config(InterpreterConfig::default())
+    }
+
+    pub fn with_config(config: InterpreterConfig) -> Self {
+        Self {
+            limits: config.limits,
+            interpreter: Interpreter::with_config(config),
+            diagnostics: Box::new(io::stderr()),
+        }
+    }
+
+    // Output of `print` statements.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.interpreter.set_output(output);
+    }
+
+    // Warnings are reported here; errors are returned to the caller instead.
+    pub fn set_diagnostics(&mut self, diagnostics: Box<dyn Write>) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn run(&mut self, source: &str) -> Result<(), Error> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let mut parser = Parser::with_limits(tokens, self.limits);
+        let statements = parser.parse()?;
+        for warning in parser.warnings() {
+            // A warning that can't be written is not worth failing the run.
+            let _ = warning.report_to(&mut self.diagnostics, "");
+        }
+        self.interpreter.interpret(&statements)
+    }
+
+    pub fn eval_expr(&mut self, source: &str) -> Result<Value, Error> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        let expression = Parser::with_limits(tokens, self.limits).parse_expression()?;
+        let value = self.interpreter.evaluate_expression(&expression)?;
+        Ok(Value::from(value))
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.interpreter.set_global(name, value);
+    }
+
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + 'static,
+    ) {
+        self.interpreter.register_fn(name, arity, function);
+    }
+
+    // Calls the function a script defined as the global `name`.
+    pub fn call(&mut self, name: &str, arguments: &[Value]) -> Result<Value, Error> {
+        let callee = self.get_global(name).ok_or_else(|| {
+            Error::new(
+                None,
+                ErrorType::RuntimeError,
+                &format!("Undefined variable {}", name),
+            )
+        })?;
+        self.interpreter.call(&callee, arguments)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.interpreter
+            .global(Symbol::intern(name))
+            .map(Value::from)
+    }
+}
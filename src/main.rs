@@ -1,32 +1,419 @@
 use std::{
     env::args,
-    fs::read_to_string,
-    io::{self, Write},
+    fs::{self, read_to_string},
+    io::{self, IsTerminal, Read, Write},
+    panic,
+    path::Path,
+    process::ExitCode,
+    rc::Rc,
+    thread,
 };
 
+use rlox::ast_printer::AstPrinter;
+use rlox::compiler::Compiler;
+use rlox::config::InterpreterConfig;
 use rlox::error::Error;
+use rlox::formatter::Formatter;
+use rlox::heap::{self, GcConfig};
 use rlox::interpreter::Interpreter;
+use rlox::ir_interpreter::IrInterpreter;
+use rlox::lowering::Lowerer;
+use rlox::native::NativeFunction;
+use rlox::optimizer::Optimizer;
 use rlox::parser::Parser;
 use rlox::scanner::Scanner;
+use rlox::serializer::{Deserializer, Serializer};
+use rlox::vm::VirtualMachine;
+use rlox::{FromArguments, Statement, Value};
 
-fn main() -> io::Result<()> {
-    let args: Vec<String> = args().collect();
+const HELP: &str = "\
+Usage: rlox [options] [command] [script] [arg...]
 
-    match args.len() {
-        1 => run_repl()?,
+Commands:
+    run        Run a script, or a compiled .loxc file (the default)
+    repl       Read and run one line at a time
+    check      Parse and resolve a script without running it
+    fmt        Print a script in canonical layout; comments are not kept
+    tokens     Print the tokens of a script
+    ast        Print the syntax tree of a script
+    compile    Write a script as a .loxc file, or to the file given with -o
 
-        2 => run_file(&args[1])?,
+A script of '-' is read from standard input, as is the script of a command
+given none while standard input is not a terminal. Arguments after the
+script of `run` are passed to it, as `argc()` and `arg(i)`.
 
-        _ => {
-            eprintln!("Usage: rlox [script]");
-            std::process::exit(64);
+Options:
+    -e <source>        Use <source> as the script
+    -D <name=value>    Define a global before the script runs
+    --vm               Run on the bytecode virtual machine
+    --ir               Run on the IR interpreter
+    --emit=ir          Print the lowered IR instead of running
+    -O                 Optimize the script first
+    -o <file>          Where `compile` writes
+    --gc-stress        Collect garbage at every safepoint
+    --gc-stats         Print garbage collector statistics on exit
+    -h, --help         Print this help
+    -V, --version      Print the version
+";
+
+// sysexits(3) codes.
+const EX_USAGE: u8 = 64;
+const EX_DATAERR: u8 = 65;
+const EX_NOINPUT: u8 = 66;
+const EX_SOFTWARE: u8 = 70;
+const EX_CANTCREAT: u8 = 73;
+
+#[derive(Clone, Copy)]
+enum Backend {
+    TreeWalker,
+    VirtualMachine,
+    Ir,
+}
+
+#[derive(Clone, Copy)]
+struct Options {
+    backend: Backend,
+    emit_ir: bool,
+    optimize: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Run,
+    Repl,
+    Check,
+    Fmt,
+    Tokens,
+    Ast,
+    Compile,
+    Help,
+    Version,
+}
+
+const COMMANDS: [(&str, Command); 9] = [
+    ("run", Command::Run),
+    ("repl", Command::Repl),
+    ("check", Command::Check),
+    ("fmt", Command::Fmt),
+    ("tokens", Command::Tokens),
+    ("ast", Command::Ast),
+    ("compile", Command::Compile),
+    ("help", Command::Help),
+    ("version", Command::Version),
+];
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        COMMANDS
+            .iter()
+            .find(|(command, _)| *command == name)
+            .map(|(_, command)| *command)
+    }
+
+    fn name(self) -> &'static str {
+        COMMANDS
+            .iter()
+            .find(|(_, command)| *command == self)
+            .map_or("run", |(name, _)| name)
+    }
+}
+
+// The command line, once parsed.
+struct Cli {
+    command: Option<Command>,
+    script: Option<String>,
+    inline: Option<String>,
+    output: Option<String>,
+    options: Options,
+    gc: GcConfig,
+    gc_stats: bool,
+    globals: Vec<(String, Value)>,
+    script_args: Vec<String>,
+}
+
+// Why rlox stopped early. Each is reported on stderr and exits with its own
+// code, so shell scripts can tell them apart.
+enum Failure {
+    Usage(String),
+    Input(String, io::Error),
+    Output(String, io::Error),
+    // The script is at fault, found before any of it ran. Even a limit, such
+    // as on nesting, counts as bad input here.
+    Script(Error),
+    // The script failed while it ran.
+    Runtime(Error),
+    // The script called `exit`, which is not reported.
+    Exit(i32),
+}
+
+// An error is in the script unless it was raised while the script ran.
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        Failure::Script(error)
+    }
+}
+
+impl Failure {
+    fn runtime(error: Error) -> Self {
+        match error.exit_status() {
+            Some(status) => Failure::Exit(status),
+            None => Failure::Runtime(error),
         }
+    }
+
+    fn report(&self) {
+        match self {
+            Failure::Usage(message) => {
+                eprintln!("rlox: {}", message);
+                eprintln!("Try 'rlox --help' for more information.");
+            }
+            Failure::Input(path, error) => {
+                eprintln!("rlox: cannot read {}: {}", path, describe(error))
+            }
+            Failure::Output(path, error) => {
+                eprintln!("rlox: cannot write {}: {}", path, describe(error))
+            }
+            Failure::Script(error) | Failure::Runtime(error) => error.report(""),
+            Failure::Exit(_) => (),
+        }
+    }
+
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Usage(_) => EX_USAGE,
+            Failure::Input(..) => EX_NOINPUT,
+            Failure::Output(..) => EX_CANTCREAT,
+            Failure::Script(_) => EX_DATAERR,
+            Failure::Runtime(_) => EX_SOFTWARE,
+            // Only the low byte reaches the parent, as with exit(3).
+            Failure::Exit(status) => *status as u8,
+        }
+    }
+}
+
+// The OS messages for the common cases carry an error number nobody needs.
+fn describe(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => "no such file or directory".to_string(),
+        io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        io::ErrorKind::IsADirectory => "is a directory".to_string(),
+        _ => error.to_string(),
+    }
+}
+
+// The tree-walking interpreter recurses on the Rust stack: as deep as `Limits`
+// lets one function body nest, in each of the calls every backend allows. That
+// is more than the main thread has, so rlox runs on a thread of its own.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn main() -> ExitCode {
+    let runner = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(|| parse_arguments().and_then(execute));
+    let result = match runner {
+        Ok(runner) => runner
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic)),
+        // Deep recursion fails sooner without the larger stack, but it runs.
+        Err(_) => parse_arguments().and_then(execute),
     };
 
-    Ok(())
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            failure.report();
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
+
+fn parse_arguments() -> Result<Cli, Failure> {
+    let mut cli = Cli {
+        command: None,
+        script: None,
+        inline: None,
+        output: None,
+        options: Options {
+            backend: Backend::TreeWalker,
+            emit_ir: false,
+            optimize: false,
+        },
+        gc: GcConfig::default(),
+        gc_stats: false,
+        globals: Vec::new(),
+        script_args: Vec::new(),
+    };
+
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => cli.command = Some(Command::Help),
+            "-V" | "--version" => cli.command = Some(Command::Version),
+            "--vm" => cli.options.backend = Backend::VirtualMachine,
+            "--ir" => cli.options.backend = Backend::Ir,
+            "--emit=ir" => cli.options.emit_ir = true,
+            "-O" => cli.options.optimize = true,
+            "--gc-stress" => cli.gc.stress = true,
+            "--gc-stats" => cli.gc_stats = true,
+            "-o" => cli.output = Some(option_value(&mut args, "-o")?),
+            "-e" => cli.inline = Some(option_value(&mut args, "-e")?),
+            "-D" => {
+                let value = option_value(&mut args, "-D")?;
+                cli.globals.push(definition(&value)?);
+            }
+            _ if arg.starts_with("-D") => cli.globals.push(definition(&arg[2..])?),
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(Failure::Usage(format!("unknown option '{}'", arg)));
+            }
+            _ => {
+                let starts = cli.command.is_none() && cli.script.is_none() && cli.inline.is_none();
+                if let Some(command) = Command::from_name(&arg).filter(|_| starts) {
+                    cli.command = Some(command);
+                    continue;
+                }
+
+                // Everything after the script of `run` is passed to the script.
+                let runs = matches!(cli.command, None | Some(Command::Run));
+                if runs && cli.inline.is_some() {
+                    cli.script_args.push(arg);
+                    cli.script_args.extend(args.by_ref());
+                } else if cli.script.is_none() {
+                    cli.script = Some(arg);
+                    if runs {
+                        cli.script_args.extend(args.by_ref());
+                    }
+                } else {
+                    return Err(Failure::Usage(format!("unexpected argument '{}'", arg)));
+                }
+            }
+        }
+    }
+
+    Ok(cli)
+}
+
+fn option_value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, Failure> {
+    args.next()
+        .ok_or_else(|| Failure::Usage(format!("option '{}' needs a value", option)))
+}
+
+// A `-D name=value` flag. The value is a number, a boolean or nil when it is
+// written like one, and a string otherwise.
+fn definition(definition: &str) -> Result<(String, Value), Failure> {
+    let Some((name, value)) = definition.split_once('=') else {
+        return Err(Failure::Usage(format!(
+            "invalid definition '{}', expected name=value",
+            definition
+        )));
+    };
+
+    let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+        return Err(Failure::Usage(format!("invalid variable name '{}'", name)));
+    }
+
+    let is_number = value.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == '-');
+    let value = match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "nil" => Value::Nil,
+        _ if is_number => value
+            .parse()
+            .map_or_else(|_| Value::from(value), Value::Number),
+        _ => Value::from(value),
+    };
+    Ok((name.to_string(), value))
+}
+
+// `argc()` and `arg(i)`, for the arguments given after the script. `arg` is
+// nil past the last one.
+fn script_arguments(arguments: Vec<String>) -> [(String, Value); 2] {
+    let count = arguments.len();
+    let argc = NativeFunction::new("argc", 0, move |_| Ok(Value::Number(count as f64)));
+    let arg = NativeFunction::new("arg", 1, move |values| {
+        let (index,): (f64,) = FromArguments::from_arguments(values)?;
+        if index < 0.0 || index.fract() != 0.0 {
+            return Err(Error::runtime(
+                "Argument index must be a non-negative integer",
+            ));
+        }
+        Ok(arguments.get(index as usize).map(String::as_str).into())
+    });
+
+    [
+        ("argc".to_string(), Value::Native(Rc::new(argc))),
+        ("arg".to_string(), Value::Native(Rc::new(arg))),
+    ]
+}
+
+fn execute(mut cli: Cli) -> Result<(), Failure> {
+    heap::configure(cli.gc);
+
+    let command = cli.command.unwrap_or(Command::Run);
+    let script_args = std::mem::take(&mut cli.script_args);
+    cli.globals.extend(script_arguments(script_args));
+
+    let result = match command {
+        Command::Help => {
+            print!("{}", HELP);
+            Ok(())
+        }
+
+        Command::Version => {
+            println!("rlox {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+
+        Command::Repl => run_repl(cli.options, &cli.globals),
+
+        Command::Run => match read_source(&cli)? {
+            Some(source) => run(source, cli.options, &cli.globals),
+            None => run_repl(cli.options, &cli.globals),
+        },
+
+        Command::Check => parse(required_source(&cli, command)?)
+            .map(|_| ())
+            .map_err(Failure::from),
+
+        Command::Fmt => {
+            let statemets = parse(required_source(&cli, command)?)?;
+            print!("{}", Formatter::new().format(&statemets)?);
+            Ok(())
+        }
+
+        Command::Ast => {
+            let statemets = parse(required_source(&cli, command)?)?;
+            print!("{}", AstPrinter::new().print(&statemets)?);
+            Ok(())
+        }
+
+        Command::Tokens => {
+            let Source::Text(source) = required_source(&cli, command)? else {
+                return Err(Failure::Usage(
+                    "tokens needs Lox source, not a compiled file".to_string(),
+                ));
+            };
+            for token in Scanner::new(&source).scan_tokens()? {
+                println!("{}", token);
+            }
+            Ok(())
+        }
+
+        Command::Compile => compile_file(&cli),
+    };
+
+    if cli.gc_stats {
+        eprintln!("{}", heap::stats());
+    }
+
+    result
 }
 
-fn run_repl() -> io::Result<()> {
+fn run_repl(options: Options, globals: &[(String, Value)]) -> Result<(), Failure> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
@@ -35,38 +422,152 @@ fn run_repl() -> io::Result<()> {
     println!("welcome to rlox repl");
     loop {
         print!("rlox:> ");
-        stdout.flush()?;
-        if let Ok(_) = stdin.read_line(&mut line) {
-            run(&line).unwrap_or_else(|err| {
-                err.report("");
-            });
-            line.clear();
-        } else {
-            break;
+        let _ = stdout.flush();
+        match stdin.read_line(&mut line) {
+            // End of input.
+            Ok(0) => break,
+            Ok(_) => {
+                match run(Source::Text(line.clone()), options, globals) {
+                    Err(Failure::Exit(status)) => return Err(Failure::Exit(status)),
+                    Err(failure) => failure.report(),
+                    Ok(()) => (),
+                }
+                line.clear();
+            }
+            Err(error) => return Err(Failure::Input("standard input".to_string(), error)),
         }
     }
 
     Ok(())
 }
 
-fn run_file(path: &str) -> io::Result<()> {
-    let source = read_to_string(path)?;
+enum Source {
+    Text(String),
+    Compiled(Vec<u8>),
+}
 
-    run(&source).unwrap_or_else(|err| {
-        err.report("");
-        std::process::exit(65);
-    });
+// The script the command line names, if any: inline, a file, or standard
+// input, either as '-' or because it is piped in.
+fn read_source(cli: &Cli) -> Result<Option<Source>, Failure> {
+    if let Some(source) = &cli.inline {
+        return Ok(Some(Source::Text(source.clone())));
+    }
 
-    Ok(())
+    let path = match cli.script.as_deref() {
+        Some(path) => path,
+        None if !io::stdin().is_terminal() => "-",
+        None => return Ok(None),
+    };
+
+    if path == "-" {
+        let mut source = String::new();
+        return match io::stdin().read_to_string(&mut source) {
+            Ok(_) => Ok(Some(Source::Text(source))),
+            Err(error) => Err(Failure::Input("standard input".to_string(), error)),
+        };
+    }
+
+    let source = if Path::new(path).extension().is_some_and(|ext| ext == "loxc") {
+        fs::read(path).map(Source::Compiled)
+    } else {
+        read_to_string(path).map(Source::Text)
+    };
+    source
+        .map(Some)
+        .map_err(|error| Failure::Input(path.to_string(), error))
+}
+
+fn required_source(cli: &Cli, command: Command) -> Result<Source, Failure> {
+    read_source(cli)?.ok_or_else(|| Failure::Usage(format!("{} needs a script", command.name())))
 }
 
-fn run(source: &str) -> Result<(), Error> {
-    let mut scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens()?;
-    let mut parser = Parser::new(tokens);
-    let statemets = parser.parse()?;
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(&statemets)?;
+fn compile_file(cli: &Cli) -> Result<(), Failure> {
+    let source = required_source(cli, Command::Compile)?;
+    let output = match (&cli.output, &cli.script) {
+        (Some(output), _) => output.clone(),
+        (None, Some(script)) if script != "-" => Path::new(script)
+            .with_extension("loxc")
+            .to_string_lossy()
+            .into_owned(),
+        _ => {
+            return Err(Failure::Usage(
+                "compile needs -o when the script is not a file".to_string(),
+            ))
+        }
+    };
+
+    let bytes = compile(source, cli.options)?;
+    fs::write(&output, bytes).map_err(|error| Failure::Output(output, error))
+}
+
+fn compile(source: Source, options: Options) -> Result<Vec<u8>, Error> {
+    let mut statemets = parse(source)?;
+    if options.optimize {
+        statemets = Optimizer::new().optimize(&statemets)?;
+    }
+
+    Serializer::new().serialize(&statemets)
+}
+
+fn parse(source: Source) -> Result<Vec<Statement>, Error> {
+    match source {
+        Source::Text(source) => {
+            let mut scanner = Scanner::new(&source);
+            let tokens = scanner.scan_tokens()?;
+            let mut parser = Parser::new(tokens);
+            let statemets = parser.parse()?;
+            for warning in parser.warnings() {
+                warning.report("");
+            }
+            Ok(statemets)
+        }
+
+        Source::Compiled(bytes) => Deserializer::new(&bytes).deserialize(),
+    }
+}
+
+fn run(source: Source, options: Options, globals: &[(String, Value)]) -> Result<(), Failure> {
+    let mut statemets = parse(source)?;
+
+    if options.optimize {
+        statemets = Optimizer::new().optimize(&statemets)?;
+    }
+
+    if options.emit_ir {
+        print!("{}", Lowerer::new().lower(&statemets)?);
+        return Ok(());
+    }
+
+    // Scripts run from the command line are as trusted as the user.
+    match options.backend {
+        Backend::TreeWalker => {
+            let mut interpreter = Interpreter::with_config(InterpreterConfig::trusted());
+            for (name, value) in globals {
+                interpreter.set_global(name, value.clone());
+            }
+            interpreter
+                .interpret(&statemets)
+                .map_err(Failure::runtime)?;
+        }
+
+        Backend::VirtualMachine => {
+            let chunk = Compiler::new().compile(&statemets)?;
+            let mut vm = VirtualMachine::with_config(InterpreterConfig::trusted());
+            for (name, value) in globals {
+                vm.set_global(name, value.clone());
+            }
+            vm.interpret(&chunk).map_err(Failure::runtime)?;
+        }
+
+        Backend::Ir => {
+            let program = Lowerer::new().lower(&statemets)?;
+            let mut interpreter = IrInterpreter::with_config(InterpreterConfig::trusted());
+            for (name, value) in globals {
+                interpreter.set_global(name, value.clone());
+            }
+            interpreter.interpret(&program).map_err(Failure::runtime)?;
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,71 @@
+// A NaN-boxed `Object` in a single 64-bit word. Numbers are stored as their own
+// bits. Every other value lives in the payload of a quiet NaN with a bit
+// pattern that arithmetic never produces: nil and booleans as small tags, and
+// heap objects as their handle with the sign bit set, plus a kind field above
+// the handle that tells strings, native functions, foreign objects and
+// functions apart.
+
+use crate::{heap::Handle, object::Object};
+
+const QUIET_NAN: u64 = 0x7ffc_0000_0000_0000;
+const SIGN_BIT: u64 = 0x8000_0000_0000_0000;
+const KIND_SHIFT: u64 = 48;
+const KIND_MASK: u64 = 0b11 << KIND_SHIFT;
+const KIND_STRING: u64 = 0;
+const KIND_NATIVE: u64 = 1;
+const KIND_FOREIGN: u64 = 2;
+const KIND_FUNCTION: u64 = 3;
+
+const TAG_NIL: u64 = 1;
+const TAG_FALSE: u64 = 2;
+const TAG_TRUE: u64 = 3;
+
+#[derive(Clone, Copy)]
+pub struct NanBox(u64);
+
+impl NanBox {
+    fn heap(kind: u64, handle: Handle) -> Self {
+        Self(SIGN_BIT | QUIET_NAN | kind << KIND_SHIFT | handle.to_bits())
+    }
+}
+
+impl From<Object> for NanBox {
+    fn from(object: Object) -> Self {
+        match object {
+            // Collapse every NaN to the canonical one so its payload cannot
+            // be mistaken for a tag.
+            Object::Number(number) if number.is_nan() => Self(f64::NAN.to_bits()),
+            Object::Number(number) => Self(number.to_bits()),
+            Object::Nil => Self(QUIET_NAN | TAG_NIL),
+            Object::Bool(false) => Self(QUIET_NAN | TAG_FALSE),
+            Object::Bool(true) => Self(QUIET_NAN | TAG_TRUE),
+            Object::String(handle) => Self::heap(KIND_STRING, handle),
+            Object::Native(handle) => Self::heap(KIND_NATIVE, handle),
+            Object::Foreign(handle) => Self::heap(KIND_FOREIGN, handle),
+            Object::Function(handle) => Self::heap(KIND_FUNCTION, handle),
+        }
+    }
+}
+
+impl From<NanBox> for Object {
+    fn from(value: NanBox) -> Self {
+        let bits = value.0;
+        if bits & QUIET_NAN != QUIET_NAN {
+            Object::Number(f64::from_bits(bits))
+        } else if bits & SIGN_BIT != 0 {
+            let handle = Handle::from_bits(bits & !(SIGN_BIT | QUIET_NAN | KIND_MASK));
+            match (bits & KIND_MASK) >> KIND_SHIFT {
+                KIND_NATIVE => Object::Native(handle),
+                KIND_FOREIGN => Object::Foreign(handle),
+                KIND_FUNCTION => Object::Function(handle),
+                _ => Object::String(handle),
+            }
+        } else {
+            match bits & !QUIET_NAN {
+                TAG_FALSE => Object::Bool(false),
+                TAG_TRUE => Object::Bool(true),
+                _ => Object::Nil,
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{fmt, rc::Rc};
+
+use crate::{
+    error::{Error, ErrorType},
+    function, heap,
+    object::Object,
+    value::Value,
+};
+
+type Function = dyn Fn(&[Value]) -> Result<Value, Error>;
+
+// A function the host program makes callable from Lox. It works on owned
+// `Value`s, so host code never sees heap handles.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<Function>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> Result<Value, Error> + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+}
+
+// Two natives are the same value only if they are the same function object.
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+// Calls `callee` with arguments every backend evaluates the same way. An error
+// the host function returns without a line is reported at the call site, when
+// there is one.
+pub(crate) fn call(
+    callee: Object,
+    arguments: &[Object],
+    line: Option<usize>,
+) -> Result<Object, Error> {
+    let Object::Native(handle) = callee else {
+        return Err(Error::new(
+            line,
+            ErrorType::RuntimeError,
+            "Can only call functions",
+        ));
+    };
+
+    let function: Rc<NativeFunction> = heap::native(handle);
+    function::check_arity(function.arity, arguments.len(), line)?;
+
+    let arguments: Vec<Value> = arguments.iter().copied().map(Value::from).collect();
+    (function.function)(&arguments)
+        .and_then(Object::try_from)
+        .map_err(|error| error.at_line(line))
+}
@@ -1,21 +1,49 @@
 use std::fmt;
 
-#[derive(Clone, PartialEq)]
+use crate::heap::{self, Handle};
+
+#[derive(Clone, Copy)]
 pub enum Object {
     Number(f64),
-    String(String),
-    True,
-    False,
+    String(Handle),
+    Bool(bool),
+    Native(Handle),
+    Foreign(Handle),
+    Function(Handle),
     Nil,
 }
 
+impl Object {
+    // The heap object this value keeps alive, if any.
+    pub fn handle(&self) -> Option<Handle> {
+        match self {
+            Self::String(handle)
+            | Self::Native(handle)
+            | Self::Foreign(handle)
+            | Self::Function(handle) => Some(*handle),
+            _ => None,
+        }
+    }
+
+    // The value as it would be written in Lox source, so a string gets its
+    // quotes back.
+    pub fn to_source(self) -> String {
+        match self {
+            Self::String(handle) => heap::with_string(handle, |string| format!("\"{}\"", string)),
+            _ => self.to_string(),
+        }
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::True => write!(f, "true"),
-            Self::False => write!(f, "false"),
+            Self::Bool(boolean) => write!(f, "{}", boolean),
             Self::Number(number) => write!(f, "{}", number),
-            Self::String(string) => write!(f, "{}", string),
+            Self::String(handle) => heap::with_string(*handle, |string| write!(f, "{}", string)),
+            Self::Native(handle) => write!(f, "{:?}", heap::native(*handle)),
+            Self::Foreign(handle) => write!(f, "{}", heap::foreign(*handle)),
+            Self::Function(handle) => write!(f, "{}", heap::function(*handle)),
             Self::Nil => write!(f, "nil"),
         }
     }
@@ -0,0 +1,320 @@
+// Operator semantics shared by every execution backend, so that the tree-walking
+// interpreter and the bytecode virtual machine agree on results and error messages.
+
+use std::{
+    cmp::Ordering,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::{
+    error::{Error, ErrorType},
+    heap::{self, Handle},
+    object::Object,
+    token_type::TokenType,
+};
+
+pub fn is_truthy(object: &Object) -> bool {
+    !matches!(object, Object::Nil | Object::Bool(false))
+}
+
+pub fn is_equal(left: &Object, right: &Object) -> bool {
+    match (left, right) {
+        (Object::Number(left), Object::Number(right)) => left == right,
+        (Object::String(left), Object::String(right)) => {
+            left == right || string_ordering(*left, *right) == Ordering::Equal
+        }
+        (Object::Bool(left), Object::Bool(right)) => left == right,
+        (Object::Native(left), Object::Native(right)) => {
+            left == right || Rc::ptr_eq(&heap::native(*left), &heap::native(*right))
+        }
+        (Object::Foreign(left), Object::Foreign(right)) => {
+            left == right || Rc::ptr_eq(&heap::foreign(*left), &heap::foreign(*right))
+        }
+        (Object::Function(left), Object::Function(right)) => left == right,
+        (Object::Nil, Object::Nil) => true,
+        _ => false,
+    }
+}
+
+fn string_ordering(left: Handle, right: Handle) -> Ordering {
+    heap::with_string(left, |left| {
+        heap::with_string(right, |right| left.cmp(right))
+    })
+}
+
+// Bitwise operators work on 64-bit integers, so the operand must be a number
+// without a fractional part that fits in an `i64`.
+fn integer_operand(object: Object, line: usize) -> Result<i64, Error> {
+    match object {
+        Object::Number(number)
+            if number.fract() == 0.0 && number >= i64::MIN as f64 && number < i64::MAX as f64 =>
+        {
+            Ok(number as i64)
+        }
+        _ => Err(Error::new(
+            line,
+            ErrorType::RuntimeError,
+            "Operand must be an integer",
+        )),
+    }
+}
+
+fn shift_amount(object: Object, line: usize) -> Result<u32, Error> {
+    let amount = integer_operand(object, line)?;
+    if (0..64).contains(&amount) {
+        Ok(amount as u32)
+    } else {
+        Err(Error::new(
+            line,
+            ErrorType::RuntimeError,
+            "Shift amount must be between 0 and 63",
+        ))
+    }
+}
+
+pub fn unary(operator: &TokenType, line: usize, right: Object) -> Result<Object, Error> {
+    match operator {
+        TokenType::Minus => match right {
+            Object::Number(num) => Ok(Object::Number(-num)),
+            _ => Ok(Object::Nil),
+        },
+
+        TokenType::Tilde => {
+            let right = integer_operand(right, line)?;
+            Ok(Object::Number(!right as f64))
+        }
+
+        TokenType::Bang => Ok(Object::Bool(!is_truthy(&right))),
+
+        _ => Err(Error::new(
+            line,
+            ErrorType::RuntimeError,
+            "Operator does not support unary operation",
+        )),
+    }
+}
+
+pub fn binary(
+    operator: &TokenType,
+    line: usize,
+    left: Object,
+    right: Object,
+) -> Result<Object, Error> {
+    match operator {
+        TokenType::Plus => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left + right)),
+            (Object::String(left), Object::String(right)) => {
+                let string = heap::with_string(left, |left| {
+                    heap::with_string(right, |right| {
+                        let mut string = String::with_capacity(left.len() + right.len());
+                        string.push_str(left);
+                        string.push_str(right);
+                        string
+                    })
+                });
+                heap::allocate_string(string)
+                    .map(Object::String)
+                    .map_err(|error| error.at_line(Some(line)))
+            }
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be either number or string",
+            )),
+        },
+
+        TokenType::Minus => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left - right)),
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be a number",
+            )),
+        },
+
+        TokenType::Star => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left * right)),
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be a number",
+            )),
+        },
+
+        TokenType::Slash => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left / right)),
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be a number",
+            )),
+        },
+
+        TokenType::Percent => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left % right)),
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be a number",
+            )),
+        },
+
+        TokenType::StarStar => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Number(left.powf(right))),
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be a number",
+            )),
+        },
+
+        TokenType::TildeSlash => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => {
+                Ok(Object::Number((left / right).floor()))
+            }
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be a number",
+            )),
+        },
+
+        TokenType::Ampersand => {
+            let left = integer_operand(left, line)?;
+            let right = integer_operand(right, line)?;
+            Ok(Object::Number((left & right) as f64))
+        }
+
+        TokenType::Pipe => {
+            let left = integer_operand(left, line)?;
+            let right = integer_operand(right, line)?;
+            Ok(Object::Number((left | right) as f64))
+        }
+
+        TokenType::Caret => {
+            let left = integer_operand(left, line)?;
+            let right = integer_operand(right, line)?;
+            Ok(Object::Number((left ^ right) as f64))
+        }
+
+        TokenType::LessLess => {
+            let left = integer_operand(left, line)?;
+            let right = shift_amount(right, line)?;
+            Ok(Object::Number((left << right) as f64))
+        }
+
+        TokenType::GreaterGreater => {
+            let left = integer_operand(left, line)?;
+            let right = shift_amount(right, line)?;
+            Ok(Object::Number((left >> right) as f64))
+        }
+
+        TokenType::EqualEqual => Ok(Object::Bool(is_equal(&left, &right))),
+
+        TokenType::BangEqual => Ok(Object::Bool(!is_equal(&left, &right))),
+
+        TokenType::Greater => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left > right)),
+            (Object::String(left), Object::String(right)) => {
+                Ok(Object::Bool(string_ordering(left, right).is_gt()))
+            }
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be either number or string",
+            )),
+        },
+
+        TokenType::GreaterEqual => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left >= right)),
+            (Object::String(left), Object::String(right)) => {
+                Ok(Object::Bool(string_ordering(left, right).is_ge()))
+            }
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be either number or string",
+            )),
+        },
+
+        TokenType::Less => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left < right)),
+            (Object::String(left), Object::String(right)) => {
+                Ok(Object::Bool(string_ordering(left, right).is_lt()))
+            }
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be either number or string",
+            )),
+        },
+
+        TokenType::LessEqual => match (left, right) {
+            (Object::Number(left), Object::Number(right)) => Ok(Object::Bool(left <= right)),
+            (Object::String(left), Object::String(right)) => {
+                Ok(Object::Bool(string_ordering(left, right).is_le()))
+            }
+            (_, _) => Err(Error::new(
+                line,
+                ErrorType::RuntimeError,
+                "Operand must be either number or string",
+            )),
+        },
+
+        _ => Err(Error::new(
+            line,
+            ErrorType::RuntimeError,
+            "Operator does not support binary opertaion",
+        )),
+    }
+}
+
+// Where `print` writes, stdout unless the host redirects it, and whether
+// scripts may print at all. Every backend prints through one of these.
+pub(crate) struct Output {
+    sink: Box<dyn Write>,
+    allowed: bool,
+}
+
+impl Output {
+    pub(crate) fn new(allowed: bool) -> Self {
+        Self {
+            sink: Box::new(io::stdout()),
+            allowed,
+        }
+    }
+
+    pub(crate) fn redirect(&mut self, sink: Box<dyn Write>) {
+        self.sink = sink;
+    }
+
+    pub(crate) fn print(&mut self, value: Object, line: usize) -> Result<(), Error> {
+        if !self.allowed {
+            return Err(Error::new(
+                line,
+                ErrorType::PermissionError,
+                "print is not allowed",
+            ));
+        }
+
+        writeln!(self.sink, "{}", value).map_err(|error| {
+            Error::new(
+                line,
+                ErrorType::RuntimeError,
+                &format!("Could not write output: {}", error),
+            )
+        })
+    }
+
+    // Called with the result of every run. After `exit` the host may end the
+    // process next, and a sink it handed over may buffer.
+    pub(crate) fn finish<T>(&mut self, result: &Result<T, Error>) {
+        if result
+            .as_ref()
+            .is_err_and(|error| error.exit_status().is_some())
+        {
+            let _ = self.sink.flush();
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{
+    error::Error,
+    expression::{
+        AssignmentExpression, BinaryExpression, CallExpression, Expression, ExpressionVisitor,
+        GetExpression, GroupingExpression, LiteralExpression, NilCoalescingExpression,
+        TernaryExpression, UnaryExpression, UpdateExpression, VariableExpression,
+    },
+    heap,
+    object::Object,
+    operation,
+    resolver::Resolver,
+    statement::{
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchArm,
+        MatchStatement, PrintStatement, ReturnStatement, Statement, StatementVisitor,
+        VariableStatement,
+    },
+};
+
+// Rewrites the statements produced by the `Parser` into an equivalent, cheaper
+// program: operators over literals are folded into a single literal and `if`
+// statements with a literal condition keep only the branch that would run.
+// An operation that would fail at runtime is left in place, so the error is
+// still raised, at its original line, when the program runs.
+pub struct Optimizer;
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn optimize(mut self, statements: &[Statement]) -> Result<Vec<Statement>, Error> {
+        let mut optimized = Vec::new();
+        for statement in statements {
+            if let Some(statement) = statement.accept(&mut self)? {
+                optimized.push(statement);
+            }
+        }
+
+        // The rewritten tree is made of new nodes, so resolve its variables again.
+        Resolver::new().resolve(&optimized)?;
+        Ok(optimized)
+    }
+
+    // A branch that folds away entirely still has to be a statement.
+    fn branch(&mut self, statement: &Statement) -> Result<Statement, Error> {
+        Ok(statement
+            .accept(self)?
+            .unwrap_or_else(|| Statement::BlockStatement(BlockStatement::new(Vec::new()))))
+    }
+}
+
+fn literal(expression: &Expression) -> Option<Object> {
+    match expression {
+        Expression::Literal(literal) => Some(literal.value),
+        _ => None,
+    }
+}
+
+// Strings produced while folding end up in the program, so they become
+// constants just like the string literals from the source.
+fn constant(value: Object) -> Expression {
+    if let Object::String(handle) = value {
+        heap::pin(handle);
+    }
+    Expression::Literal(LiteralExpression::new(value))
+}
+
+impl StatementVisitor<Option<Statement>> for Optimizer {
+    fn visit_expression_statement(
+        &mut self,
+        statement: &ExpressionStatement,
+    ) -> Result<Option<Statement>, Error> {
+        let expression = statement.expression.accept(self)?;
+        Ok(Some(Statement::ExpressionStatement(
+            ExpressionStatement::new(expression),
+        )))
+    }
+
+    fn visit_print_statement(
+        &mut self,
+        statement: &PrintStatement,
+    ) -> Result<Option<Statement>, Error> {
+        let expression = statement.expression.accept(self)?;
+        Ok(Some(Statement::PrintStatement(PrintStatement::new(
+            statement.keyword.clone(),
+            expression,
+        ))))
+    }
+
+    fn visit_variable_statement(
+        &mut self,
+        statement: &VariableStatement,
+    ) -> Result<Option<Statement>, Error> {
+        let initializer = statement.initializer.accept(self)?;
+        Ok(Some(Statement::VariableStatement(VariableStatement::new(
+            statement.identifier.clone(),
+            Some(initializer),
+        ))))
+    }
+
+    fn visit_block_statement(
+        &mut self,
+        statement: &BlockStatement,
+    ) -> Result<Option<Statement>, Error> {
+        let mut statements = Vec::new();
+        for statement in &statement.statements {
+            if let Some(statement) = statement.accept(self)? {
+                statements.push(statement);
+            }
+        }
+        Ok(Some(Statement::BlockStatement(BlockStatement::new(
+            statements,
+        ))))
+    }
+
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<Option<Statement>, Error> {
+        let conditional = statement.conditional.accept(self)?;
+
+        if let Some(condition) = literal(&conditional) {
+            return if operation::is_truthy(&condition) {
+                statement.then_branch.accept(self)
+            } else if let Some(else_branch) = &statement.else_branch {
+                else_branch.accept(self)
+            } else {
+                Ok(None)
+            };
+        }
+
+        let then_branch = self.branch(&statement.then_branch)?;
+        let else_branch = match &statement.else_branch {
+            Some(else_branch) => else_branch.accept(self)?,
+            None => None,
+        };
+        Ok(Some(Statement::IfStatement(IfStatement::new(
+            conditional,
+            then_branch,
+            else_branch,
+        ))))
+    }
+
+    fn visit_match_statement(
+        &mut self,
+        statement: &MatchStatement,
+    ) -> Result<Option<Statement>, Error> {
+        let scrutinee = statement.scrutinee.accept(self)?;
+
+        let mut arms = Vec::new();
+        for arm in &statement.arms {
+            let mut patterns = Vec::new();
+            for pattern in &arm.patterns {
+                patterns.push(pattern.accept(self)?);
+            }
+            arms.push(MatchArm::new(patterns, self.branch(&arm.body)?));
+        }
+
+        let default_branch = match &statement.default_branch {
+            Some(default_branch) => default_branch.accept(self)?,
+            None => None,
+        };
+        Ok(Some(Statement::MatchStatement(MatchStatement::new(
+            scrutinee,
+            arms,
+            default_branch,
+        ))))
+    }
+
+    fn visit_function_statement(
+        &mut self,
+        statement: &FunctionStatement,
+    ) -> Result<Option<Statement>, Error> {
+        let mut body = Vec::new();
+        for statement in statement.body.iter() {
+            if let Some(statement) = statement.accept(self)? {
+                body.push(statement);
+            }
+        }
+        Ok(Some(Statement::FunctionStatement(FunctionStatement::new(
+            statement.name.clone(),
+            statement.parameters.clone(),
+            body,
+        ))))
+    }
+
+    fn visit_return_statement(
+        &mut self,
+        statement: &ReturnStatement,
+    ) -> Result<Option<Statement>, Error> {
+        let value = statement.value.accept(self)?;
+        Ok(Some(Statement::ReturnStatement(ReturnStatement::new(
+            statement.keyword.clone(),
+            Some(value),
+        ))))
+    }
+}
+
+impl ExpressionVisitor<Expression> for Optimizer {
+    fn visit_literal_expression(
+        &mut self,
+        expression: &LiteralExpression,
+    ) -> Result<Expression, Error> {
+        Ok(Expression::Literal(LiteralExpression::new(
+            expression.value,
+        )))
+    }
+
+    fn visit_unary_expression(
+        &mut self,
+        expression: &UnaryExpression,
+    ) -> Result<Expression, Error> {
+        let right = expression.right.accept(self)?;
+        let operator = &expression.operator;
+
+        if let Some(value) = literal(&right) {
+            if let Ok(value) = operation::unary(&operator.ttype, operator.line, value) {
+                return Ok(constant(value));
+            }
+        }
+
+        Ok(Expression::Unary(UnaryExpression::new(
+            operator.clone(),
+            right,
+        )))
+    }
+
+    fn visit_binary_expression(
+        &mut self,
+        expression: &BinaryExpression,
+    ) -> Result<Expression, Error> {
+        let left = expression.left.accept(self)?;
+        let right = expression.right.accept(self)?;
+        let operator = &expression.operator;
+
+        if let (Some(left), Some(right)) = (literal(&left), literal(&right)) {
+            if let Ok(value) = operation::binary(&operator.ttype, operator.line, left, right) {
+                return Ok(constant(value));
+            }
+        }
+
+        Ok(Expression::Binary(BinaryExpression::new(
+            left,
+            operator.clone(),
+            right,
+        )))
+    }
+
+    fn visit_grouping_expression(
+        &mut self,
+        expression: &GroupingExpression,
+    ) -> Result<Expression, Error> {
+        let inner = expression.expressions.accept(self)?;
+        if literal(&inner).is_some() {
+            Ok(inner)
+        } else {
+            Ok(Expression::Grouping(GroupingExpression::new(inner)))
+        }
+    }
+
+    fn visit_variable_expression(
+        &mut self,
+        expression: &VariableExpression,
+    ) -> Result<Expression, Error> {
+        Ok(Expression::VariableExpression(VariableExpression::new(
+            expression.identifier.clone(),
+        )))
+    }
+
+    fn visit_ternary_expression(
+        &mut self,
+        expression: &TernaryExpression,
+    ) -> Result<Expression, Error> {
+        let conditional = expression.conditional.accept(self)?;
+
+        // Only the chosen branch would ever be evaluated.
+        if let Some(condition) = literal(&conditional) {
+            return if operation::is_truthy(&condition) {
+                expression.then_branch.accept(self)
+            } else {
+                expression.else_branch.accept(self)
+            };
+        }
+
+        Ok(Expression::Ternary(TernaryExpression::new(
+            conditional,
+            expression.then_branch.accept(self)?,
+            expression.else_branch.accept(self)?,
+        )))
+    }
+
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<Expression, Error> {
+        let left = expression.left.accept(self)?;
+
+        match literal(&left) {
+            Some(Object::Nil) => expression.right.accept(self),
+            Some(_) => Ok(left),
+            None => Ok(Expression::NilCoalescing(NilCoalescingExpression::new(
+                left,
+                expression.operator.clone(),
+                expression.right.accept(self)?,
+            ))),
+        }
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<Expression, Error> {
+        Ok(Expression::Assignment(AssignmentExpression::new(
+            expression.identifier.clone(),
+            expression.operator.clone(),
+            expression.value.accept(self)?,
+        )))
+    }
+
+    fn visit_update_expression(
+        &mut self,
+        expression: &UpdateExpression,
+    ) -> Result<Expression, Error> {
+        Ok(Expression::Update(UpdateExpression::new(
+            expression.identifier.clone(),
+            expression.operator.clone(),
+            expression.prefix,
+        )))
+    }
+
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<Expression, Error> {
+        let callee = expression.callee.accept(self)?;
+        let mut arguments = Vec::new();
+        for argument in &expression.arguments {
+            arguments.push(argument.accept(self)?);
+        }
+        Ok(Expression::Call(CallExpression::new(
+            callee,
+            expression.paren.clone(),
+            arguments,
+        )))
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<Expression, Error> {
+        Ok(Expression::Get(GetExpression::new(
+            expression.object.accept(self)?,
+            expression.name.clone(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Optimizer;
+    use crate::{
+        formatter::Formatter, interpreter::Interpreter, parser::Parser, scanner::Scanner,
+        statement::Statement,
+    };
+
+    fn optimize(source: &str) -> Vec<Statement> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        Optimizer::new().optimize(&statements).unwrap()
+    }
+
+    fn optimized(source: &str) -> String {
+        Formatter::new().format(&optimize(source)).unwrap()
+    }
+
+    #[test]
+    fn folds_arithmetic_and_concatenation() {
+        assert_eq!(optimized("print 1 + 2 * 3 - -4;"), "print 11;\n");
+        assert_eq!(optimized("print (2 ** 3) % 5;"), "print 3;\n");
+        assert_eq!(
+            optimized("print \"con\" + \"cat\" + \"enated\";"),
+            "print \"concatenated\";\n"
+        );
+        assert_eq!(
+            optimized("var x = 1; print x + 2 * 3;"),
+            "var x = 1;\nprint x + 6;\n"
+        );
+    }
+
+    #[test]
+    fn removes_branches_that_never_run() {
+        assert_eq!(optimized("if (false) print 1;"), "");
+        assert_eq!(optimized("if (1 > 2) print 1; else print 2;"), "print 2;\n");
+        assert_eq!(optimized("if (!nil) print 1; else print 2;"), "print 1;\n");
+        assert_eq!(optimized("print false ? 1 : 2;"), "print 2;\n");
+    }
+
+    #[test]
+    fn failing_operations_keep_their_line() {
+        let statements = optimize("var x = 1 + 2;\n\nprint 1 / \"a\";");
+        assert_eq!(
+            Formatter::new().format(&statements).unwrap(),
+            "var x = 3;\nprint 1 / \"a\";\n"
+        );
+
+        let error = Interpreter::new().interpret(&statements).unwrap_err();
+        assert_eq!(error.line(), Some(3));
+    }
+}
@@ -1,26 +1,53 @@
 use crate::{
     error::{Error, ErrorType},
     expression::{
-        BinaryExpression, Expression, GroupingExpression, LiteralExpression, UnaryExpression,
-        VariableExpression,
+        AssignmentExpression, BinaryExpression, CallExpression, Expression, GetExpression,
+        GroupingExpression, LiteralExpression, NilCoalescingExpression, TernaryExpression,
+        UnaryExpression, UpdateExpression, VariableExpression,
     },
+    limits::Limits,
     object::Object,
+    resolver::Resolver,
     statement::{
-        BlockStatement, ExpressionStatement, IfStatement, PrintStatement, Statement,
-        VariableStatement,
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchArm,
+        MatchStatement, PrintStatement, ReturnStatement, Statement, VariableStatement,
     },
     token::Token,
     token_type::TokenType,
 };
 
+const MAX_ARGUMENTS: usize = 255;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    warnings: Vec<Error>,
+    depth: usize,
+    max_depth: Option<usize>,
+    // How many function bodies enclose the current token.
+    functions: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self::with_limits(tokens, Limits::default())
+    }
+
+    // Only the nesting depth applies to parsing; the other limits are for the
+    // `Interpreter`.
+    pub fn with_limits(tokens: Vec<Token>, limits: Limits) -> Self {
+        Self {
+            tokens,
+            current: 0,
+            warnings: Vec::new(),
+            depth: 0,
+            max_depth: limits.max_depth,
+            functions: 0,
+        }
+    }
+
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
     }
 
     pub fn parse(&mut self) -> Result<Vec<Statement>, Error> {
@@ -30,24 +57,45 @@ impl Parser {
             statements.push(self.declaration()?);
         }
 
+        Resolver::new().resolve(&statements)?;
         Ok(statements)
     }
 
+    // Parses source that must be exactly one expression, such as a host asking
+    // for the value of `x * 2`.
+    pub(crate) fn parse_expression(&mut self) -> Result<Expression, Error> {
+        let expression = self.expression()?;
+        if !self.is_at_end() {
+            return Err(self.parse_error("Expect end of expression"));
+        }
+        Ok(expression)
+    }
+
     fn declaration(&mut self) -> Result<Statement, Error> {
         if self.does_match(&[TokenType::Var]) {
             self.var_declaration()
+        } else if self.does_match(&[TokenType::Fun]) {
+            self.nested(Self::function_declaration)
         } else {
             self.statement()
         }
     }
 
     fn statement(&mut self) -> Result<Statement, Error> {
+        self.nested(Self::statement_kind)
+    }
+
+    fn statement_kind(&mut self) -> Result<Statement, Error> {
         if self.does_match(&[TokenType::Print]) {
             self.print_statement()
         } else if self.does_match(&[TokenType::LeftBrace]) {
             self.block()
         } else if self.does_match(&[TokenType::If]) {
             self.if_statement()
+        } else if self.does_match(&[TokenType::Match]) {
+            self.match_statement()
+        } else if self.does_match(&[TokenType::Return]) {
+            self.return_statement()
         } else {
             self.expression_statement()
         }
@@ -69,7 +117,63 @@ impl Parser {
         )))
     }
 
+    fn match_statement(&mut self) -> Result<Statement, Error> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'match'")?;
+        let scrutinee = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after match value")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before match arms")?;
+
+        let mut arms = Vec::new();
+        let mut default_branch = None;
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let line = self.peek().line;
+            let mut patterns = Vec::new();
+            let mut is_default = false;
+
+            loop {
+                if self.check_wildcard() {
+                    self.advance();
+                    is_default = true;
+                } else {
+                    patterns.push(self.expression()?);
+                }
+
+                if !self.does_match(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenType::FatArrow, "Expect '=>' after match pattern")?;
+            let body = self.statement()?;
+
+            if default_branch.is_some() {
+                self.warnings.push(Error::new(
+                    line,
+                    ErrorType::ParsingWarning,
+                    "Unreachable match arm after '_'",
+                ));
+            } else if is_default {
+                default_branch = Some(body);
+            } else {
+                arms.push(MatchArm::new(patterns, body));
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after match arms")?;
+
+        Ok(Statement::MatchStatement(MatchStatement::new(
+            scrutinee,
+            arms,
+            default_branch,
+        )))
+    }
+
     fn block(&mut self) -> Result<Statement, Error> {
+        Ok(Statement::BlockStatement(BlockStatement::new(
+            self.block_statements()?,
+        )))
+    }
+
+    fn block_statements(&mut self) -> Result<Vec<Statement>, Error> {
         let mut statements = Vec::new();
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
@@ -77,7 +181,60 @@ impl Parser {
         }
         self.consume(TokenType::RightBrace, "Expect '}' after block")?;
 
-        Ok(Statement::BlockStatement(BlockStatement::new(statements)))
+        Ok(statements)
+    }
+
+    fn function_declaration(&mut self) -> Result<Statement, Error> {
+        let name = self.consume(TokenType::Identifier, "Expect function name")?;
+        self.consume(TokenType::LeftParen, "Expect '(' after function name")?;
+
+        let mut parameters = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if parameters.len() >= MAX_ARGUMENTS {
+                    return Err(self.parse_error(&format!(
+                        "Can't have more than {} parameters",
+                        MAX_ARGUMENTS
+                    )));
+                }
+                parameters.push(self.consume(TokenType::Identifier, "Expect parameter name")?);
+
+                if !self.does_match(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body")?;
+
+        self.functions += 1;
+        let body = self.block_statements();
+        self.functions -= 1;
+
+        Ok(Statement::FunctionStatement(FunctionStatement::new(
+            name, parameters, body?,
+        )))
+    }
+
+    fn return_statement(&mut self) -> Result<Statement, Error> {
+        let keyword = self.previous();
+        if self.functions == 0 {
+            return Err(Error::new(
+                keyword.line,
+                ErrorType::ParsingError,
+                "Can't return from top-level code",
+            ));
+        }
+
+        let mut value = None;
+        if !self.check(&TokenType::Semicolon) {
+            value = Some(self.expression()?);
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after return value")?;
+
+        Ok(Statement::ReturnStatement(ReturnStatement::new(
+            keyword, value,
+        )))
     }
 
     fn var_declaration(&mut self) -> Result<Statement, Error> {
@@ -97,11 +254,14 @@ impl Parser {
     }
 
     fn print_statement(&mut self) -> Result<Statement, Error> {
+        let keyword = self.previous();
         let value = self.expression()?;
 
         self.consume(TokenType::Semicolon, "Expect ';' after value")?;
 
-        Ok(Statement::PrintStatement(PrintStatement::new(value)))
+        Ok(Statement::PrintStatement(PrintStatement::new(
+            keyword, value,
+        )))
     }
 
     fn expression_statement(&mut self) -> Result<Statement, Error> {
@@ -115,79 +275,257 @@ impl Parser {
     }
 
     fn expression(&mut self) -> Result<Expression, Error> {
-        self.equality()
+        self.nested(Self::assignment)
     }
 
-    fn equality(&mut self) -> Result<Expression, Error> {
-        let mut expression = self.comparison()?;
+    fn assignment(&mut self) -> Result<Expression, Error> {
+        let expression = self.ternary()?;
 
-        while self.does_match(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+        if self.does_match(&[
+            TokenType::Equal,
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
             let operator = self.previous();
-            let right = self.comparison()?;
-            expression = Expression::Binary(BinaryExpression::new(expression, operator, right));
+            let value = self.nested(Self::assignment)?;
+
+            if let Expression::VariableExpression(variable) = expression {
+                return Ok(Expression::Assignment(AssignmentExpression::new(
+                    variable.identifier,
+                    operator,
+                    value,
+                )));
+            }
+
+            return Err(Error::new(
+                operator.line,
+                ErrorType::ParsingError,
+                "Invalid assignment target",
+            ));
         }
 
         Ok(expression)
     }
 
-    fn comparison(&mut self) -> Result<Expression, Error> {
-        let mut expression = self.term()?;
+    fn ternary(&mut self) -> Result<Expression, Error> {
+        let expression = self.nil_coalescing()?;
+
+        if self.does_match(&[TokenType::Question]) {
+            let then_branch = self.expression()?;
+            self.consume(TokenType::Colon, "Expect ':' after then branch")?;
+            let else_branch = self.nested(Self::ternary)?;
+            return Ok(Expression::Ternary(TernaryExpression::new(
+                expression,
+                then_branch,
+                else_branch,
+            )));
+        }
 
-        while self.does_match(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
+        Ok(expression)
+    }
+
+    fn nil_coalescing(&mut self) -> Result<Expression, Error> {
+        let mut expression = self.equality()?;
+
+        while self.does_match(&[TokenType::QuestionQuestion]) {
             let operator = self.previous();
-            let right = self.term()?;
-            expression = Expression::Binary(BinaryExpression::new(expression, operator, right));
+            let right = self.nested(Self::equality)?;
+            expression = Expression::NilCoalescing(NilCoalescingExpression::new(
+                expression, operator, right,
+            ));
         }
 
-        return Ok(expression);
+        Ok(expression)
+    }
+
+    fn equality(&mut self) -> Result<Expression, Error> {
+        self.binary(
+            &[TokenType::BangEqual, TokenType::EqualEqual],
+            Self::bitwise_or,
+        )
+    }
+
+    // '|', '^' and '&' keep C's relative order but bind tighter than equality,
+    // so `x & 1 == 0` means `(x & 1) == 0`.
+    fn bitwise_or(&mut self) -> Result<Expression, Error> {
+        self.binary(&[TokenType::Pipe], Self::bitwise_xor)
+    }
+
+    fn bitwise_xor(&mut self) -> Result<Expression, Error> {
+        self.binary(&[TokenType::Caret], Self::bitwise_and)
+    }
+
+    fn bitwise_and(&mut self) -> Result<Expression, Error> {
+        self.binary(&[TokenType::Ampersand], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expression, Error> {
+        self.binary(
+            &[
+                TokenType::Greater,
+                TokenType::GreaterEqual,
+                TokenType::Less,
+                TokenType::LessEqual,
+            ],
+            Self::shift,
+        )
+    }
+
+    fn shift(&mut self) -> Result<Expression, Error> {
+        self.binary(
+            &[TokenType::LessLess, TokenType::GreaterGreater],
+            Self::term,
+        )
     }
 
     fn term(&mut self) -> Result<Expression, Error> {
-        let mut expression = self.factor()?;
+        self.binary(&[TokenType::Minus, TokenType::Plus], Self::factor)
+    }
+
+    fn factor(&mut self) -> Result<Expression, Error> {
+        self.binary(
+            &[
+                TokenType::Star,
+                TokenType::Slash,
+                TokenType::Percent,
+                TokenType::TildeSlash,
+            ],
+            Self::unary,
+        )
+    }
 
-        while self.does_match(&[TokenType::Minus, TokenType::Plus]) {
+    fn unary(&mut self) -> Result<Expression, Error> {
+        if self.does_match(&[TokenType::Bang, TokenType::Minus, TokenType::Tilde]) {
             let operator = self.previous();
-            let right = self.factor()?;
-            expression = Expression::Binary(BinaryExpression::new(expression, operator, right));
+            let right = self.nested(Self::unary)?;
+            Ok(Expression::Unary(UnaryExpression::new(operator, right)))
+        } else {
+            self.power()
         }
-
-        return Ok(expression);
     }
 
-    fn factor(&mut self) -> Result<Expression, Error> {
-        let mut expression = self.unary()?;
+    // '**' binds tighter than a unary operator on its left and is right-associative,
+    // so `-2 ** 2` is `-(2 ** 2)` and `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+    // The exponent itself may be a unary expression, as in `2 ** -1`.
+    fn power(&mut self) -> Result<Expression, Error> {
+        let expression = self.prefix()?;
 
-        while self.does_match(&[TokenType::Star, TokenType::Slash]) {
+        if self.does_match(&[TokenType::StarStar]) {
             let operator = self.previous();
-            let right = self.unary()?;
-            expression = Expression::Binary(BinaryExpression::new(expression, operator, right));
+            let right = self.nested(Self::unary)?;
+            return Ok(Expression::Binary(BinaryExpression::new(
+                expression, operator, right,
+            )));
         }
 
-        return Ok(expression);
+        Ok(expression)
     }
 
-    fn unary(&mut self) -> Result<Expression, Error> {
-        if self.does_match(&[TokenType::Bang, TokenType::Minus]) {
+    // `++x` stands where an operand does, so `++x ** 2` is `(++x) ** 2`.
+    fn prefix(&mut self) -> Result<Expression, Error> {
+        if self.does_match(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
             let operator = self.previous();
-            let right = self.unary()?;
-            Ok(Expression::Unary(UnaryExpression::new(operator, right)))
+            let operand = self.postfix()?;
+            return self.update(operand, operator, true);
+        }
+
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expression, Error> {
+        let expression = self.call()?;
+
+        if self.does_match(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            return self.update(expression, operator, false);
+        }
+
+        Ok(expression)
+    }
+
+    // Every call or property access in a chain such as `ctx.header("x")()`
+    // nests one level deeper.
+    fn call(&mut self) -> Result<Expression, Error> {
+        let depth = self.depth;
+        let mut expression = self.primary()?;
+
+        loop {
+            if self.does_match(&[TokenType::LeftParen]) {
+                self.enter()?;
+                expression = self.finish_call(expression)?;
+            } else if self.does_match(&[TokenType::Dot]) {
+                self.enter()?;
+                let name = self.consume(TokenType::Identifier, "Expect property name after '.'")?;
+                expression = Expression::Get(GetExpression::new(expression, name));
+            } else {
+                break;
+            }
+        }
+
+        self.depth = depth;
+        Ok(expression)
+    }
+
+    fn finish_call(&mut self, callee: Expression) -> Result<Expression, Error> {
+        let mut arguments = Vec::new();
+
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    return Err(self.parse_error(&format!(
+                        "Can't have more than {} arguments",
+                        MAX_ARGUMENTS
+                    )));
+                }
+                arguments.push(self.expression()?);
+
+                if !self.does_match(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments")?;
+
+        Ok(Expression::Call(CallExpression::new(
+            callee, paren, arguments,
+        )))
+    }
+
+    // Only a variable can be incremented or decremented.
+    fn update(
+        &mut self,
+        operand: Expression,
+        operator: Token,
+        prefix: bool,
+    ) -> Result<Expression, Error> {
+        if let Expression::VariableExpression(variable) = operand {
+            Ok(Expression::Update(UpdateExpression::new(
+                variable.identifier,
+                operator,
+                prefix,
+            )))
         } else {
-            self.primary()
+            Err(Error::new(
+                operator.line,
+                ErrorType::ParsingError,
+                &format!("Invalid operand for '{}'", operator.lexeme),
+            ))
         }
     }
 
     fn primary(&mut self) -> Result<Expression, Error> {
         if self.does_match(&[TokenType::True]) {
-            return Ok(Expression::Literal(LiteralExpression::new(Object::True)));
+            return Ok(Expression::Literal(LiteralExpression::new(Object::Bool(
+                true,
+            ))));
         }
 
         if self.does_match(&[TokenType::False]) {
-            return Ok(Expression::Literal(LiteralExpression::new(Object::False)));
+            return Ok(Expression::Literal(LiteralExpression::new(Object::Bool(
+                false,
+            ))));
         }
 
         if self.does_match(&[TokenType::Nil]) {
@@ -218,6 +556,50 @@ impl Parser {
         )))
     }
 
+    // Operators that chain left to right, as in `1 + 2 + 3`, are parsed in a
+    // loop and evaluated in one, so a long chain is not nesting; only each
+    // right operand is.
+    fn binary(
+        &mut self,
+        operators: &[TokenType],
+        operand: fn(&mut Self) -> Result<Expression, Error>,
+    ) -> Result<Expression, Error> {
+        let mut expression = operand(self)?;
+
+        while self.does_match(operators) {
+            let operator = self.previous();
+            let right = self.nested(operand)?;
+            expression = Expression::Binary(BinaryExpression::new(expression, operator, right));
+        }
+
+        Ok(expression)
+    }
+
+    // Every construct that nests statements or expressions goes through here,
+    // so pathological input fails with a `LimitError` instead of overflowing
+    // the stack.
+    fn nested<T>(&mut self, parse: fn(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        let depth = self.depth;
+        self.enter()?;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    fn enter(&mut self) -> Result<(), Error> {
+        if let Some(max_depth) = self.max_depth {
+            if self.depth >= max_depth {
+                return Err(Error::new(
+                    self.peek().line,
+                    ErrorType::LimitError,
+                    &format!("Nesting exceeds the maximum depth of {}", max_depth),
+                ));
+            }
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
     fn consume(&mut self, ttype: TokenType, message: &str) -> Result<Token, Error> {
         if self.check(&ttype) {
             return Ok(self.advance());
@@ -237,6 +619,10 @@ impl Parser {
         false
     }
 
+    fn check_wildcard(&self) -> bool {
+        self.check(&TokenType::Identifier) && self.peek().lexeme.as_str() == "_"
+    }
+
     fn check(&self, ttype: &TokenType) -> bool {
         if self.is_at_end() {
             false
@@ -256,8 +642,8 @@ impl Parser {
         self.peek().ttype == TokenType::EOF
     }
 
-    fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
     }
 
     fn previous(&self) -> Token {
@@ -0,0 +1,212 @@
+use crate::{
+    error::{Error, ErrorType},
+    expression::{
+        AssignmentExpression, BinaryExpression, Binding, CallExpression, ExpressionVisitor,
+        GetExpression, GroupingExpression, LiteralExpression, NilCoalescingExpression,
+        TernaryExpression, UnaryExpression, UpdateExpression, VariableExpression,
+    },
+    interner::Symbol,
+    statement::{
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchStatement,
+        PrintStatement, ReturnStatement, Statement, StatementVisitor, VariableStatement,
+    },
+    token::Token,
+};
+
+// Walks the statements before they run and gives every variable declared in a
+// block a fixed slot in that block's frame. References to those variables are
+// bound to (depth, slot), where depth counts the frames between the reference
+// and the declaration. Anything not found in an enclosing block stays global.
+// A function body is a frame of its own, with the parameters in its first
+// slots.
+pub struct Resolver {
+    scopes: Vec<Vec<Symbol>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self { scopes: Vec::new() }
+    }
+
+    pub fn resolve(mut self, statements: &[Statement]) -> Result<(), Error> {
+        for statement in statements {
+            statement.accept(&mut self)?;
+        }
+        Ok(())
+    }
+
+    fn declare(&mut self, identifier: &Token) -> Binding {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Binding::Global;
+        };
+
+        // Redeclaring a variable in the same block reuses its slot.
+        let slot = scope
+            .iter()
+            .rposition(|name| *name == identifier.lexeme)
+            .unwrap_or_else(|| {
+                scope.push(identifier.lexeme);
+                scope.len() - 1
+            });
+        Binding::Local { depth: 0, slot }
+    }
+
+    fn lookup(&self, identifier: &Token) -> Binding {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(slot) = scope.iter().rposition(|name| *name == identifier.lexeme) {
+                return Binding::Local { depth, slot };
+            }
+        }
+        Binding::Global
+    }
+}
+
+impl StatementVisitor<()> for Resolver {
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) -> Result<(), Error> {
+        statement.expression.accept(self)
+    }
+
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<(), Error> {
+        statement.expression.accept(self)
+    }
+
+    fn visit_variable_statement(&mut self, statement: &VariableStatement) -> Result<(), Error> {
+        // The initializer still sees any outer variable of the same name.
+        statement.initializer.accept(self)?;
+        statement.binding.set(self.declare(&statement.identifier));
+        Ok(())
+    }
+
+    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<(), Error> {
+        self.scopes.push(Vec::new());
+        let result = statement
+            .statements
+            .iter()
+            .try_for_each(|statement| statement.accept(self));
+        self.scopes.pop();
+        result
+    }
+
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<(), Error> {
+        statement.conditional.accept(self)?;
+        statement.then_branch.accept(self)?;
+        if let Some(else_branch) = &statement.else_branch {
+            else_branch.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<(), Error> {
+        statement.scrutinee.accept(self)?;
+        for arm in &statement.arms {
+            for pattern in &arm.patterns {
+                pattern.accept(self)?;
+            }
+            arm.body.accept(self)?;
+        }
+        if let Some(default_branch) = &statement.default_branch {
+            default_branch.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<(), Error> {
+        // The name is declared first, so the body can call the function.
+        statement.binding.set(self.declare(&statement.name));
+
+        // Every parameter needs a slot of its own to receive its argument.
+        let mut parameters: Vec<Symbol> = Vec::new();
+        for parameter in &statement.parameters {
+            if parameters.contains(&parameter.lexeme) {
+                return Err(Error::new(
+                    parameter.line,
+                    ErrorType::ParsingError,
+                    &format!("Duplicate parameter {}", parameter.lexeme),
+                ));
+            }
+            parameters.push(parameter.lexeme);
+        }
+
+        self.scopes.push(parameters);
+        let result = statement
+            .body
+            .iter()
+            .try_for_each(|statement| statement.accept(self));
+        self.scopes.pop();
+        result
+    }
+
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<(), Error> {
+        statement.value.accept(self)
+    }
+}
+
+impl ExpressionVisitor<()> for Resolver {
+    fn visit_literal_expression(&mut self, _expression: &LiteralExpression) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn visit_unary_expression(&mut self, expression: &UnaryExpression) -> Result<(), Error> {
+        expression.right.accept(self)
+    }
+
+    fn visit_binary_expression(&mut self, expression: &BinaryExpression) -> Result<(), Error> {
+        expression.left.accept(self)?;
+        expression.right.accept(self)
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &GroupingExpression) -> Result<(), Error> {
+        expression.expressions.accept(self)
+    }
+
+    fn visit_variable_expression(&mut self, expression: &VariableExpression) -> Result<(), Error> {
+        expression.binding.set(self.lookup(&expression.identifier));
+        Ok(())
+    }
+
+    fn visit_ternary_expression(&mut self, expression: &TernaryExpression) -> Result<(), Error> {
+        expression.conditional.accept(self)?;
+        expression.then_branch.accept(self)?;
+        expression.else_branch.accept(self)
+    }
+
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<(), Error> {
+        expression.left.accept(self)?;
+        expression.right.accept(self)
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<(), Error> {
+        expression.value.accept(self)?;
+        expression.binding.set(self.lookup(&expression.identifier));
+        Ok(())
+    }
+
+    fn visit_update_expression(&mut self, expression: &UpdateExpression) -> Result<(), Error> {
+        expression.binding.set(self.lookup(&expression.identifier));
+        Ok(())
+    }
+
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<(), Error> {
+        expression.callee.accept(self)?;
+        for argument in &expression.arguments {
+            argument.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<(), Error> {
+        expression.object.accept(self)
+    }
+}
@@ -2,6 +2,8 @@ use std::collections::HashMap;
 
 use crate::{
     error::{Error, ErrorType},
+    heap::{self, Handle},
+    interner::Symbol,
     object::Object,
     token::Token,
     token_type::TokenType,
@@ -14,6 +16,8 @@ pub struct Scanner {
 
     tokens: Vec<Token>,
     keywords: HashMap<String, TokenType>,
+    // Equal string literals share one pinned heap string.
+    constants: HashMap<String, Handle>,
 
     start: usize,
     current: usize,
@@ -28,6 +32,7 @@ impl Scanner {
 
             source_len: source.len(),
             keywords: HashMap::new(),
+            constants: HashMap::new(),
             tokens: Vec::new(),
 
             start: 0,
@@ -39,6 +44,12 @@ impl Scanner {
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Error> {
         self.init_keywords();
 
+        // A `#!` line lets a script be run directly; it is not Lox.
+        if self.source.starts_with("#!") {
+            self.comment();
+            self.start = self.current;
+        }
+
         while !self.is_eof() {
             self.scan_token()?;
             self.start = self.current;
@@ -72,22 +83,78 @@ impl Scanner {
 
             '.' => self.add_token_without_literal(TokenType::Dot),
 
-            '+' => self.add_token_without_literal(TokenType::Plus),
+            ':' => self.add_token_without_literal(TokenType::Colon),
+
+            '?' => {
+                if self.does_match('?') {
+                    self.add_token_without_literal(TokenType::QuestionQuestion);
+                } else {
+                    self.add_token_without_literal(TokenType::Question);
+                }
+            }
+
+            '+' => {
+                if self.does_match('=') {
+                    self.add_token_without_literal(TokenType::PlusEqual);
+                } else if self.does_match('+') {
+                    self.add_token_without_literal(TokenType::PlusPlus);
+                } else {
+                    self.add_token_without_literal(TokenType::Plus);
+                }
+            }
+
+            // `--` is the decrement, one token, except right after an operand
+            // that is not a name: there is nothing to decrement there, so
+            // `1--1` and `f()--1` still mean `1 - -1`. Elsewhere two minus
+            // signs need a space, as in `x - -1` and `- -1`.
+            '-' => {
+                if self.does_match('=') {
+                    self.add_token_without_literal(TokenType::MinusEqual);
+                } else if !self.follows_value() && self.does_match('-') {
+                    self.add_token_without_literal(TokenType::MinusMinus);
+                } else {
+                    self.add_token_without_literal(TokenType::Minus);
+                }
+            }
 
-            '-' => self.add_token_without_literal(TokenType::Minus),
+            '%' => self.add_token_without_literal(TokenType::Percent),
 
-            '*' => self.add_token_without_literal(TokenType::Star),
+            '*' => {
+                if self.does_match('*') {
+                    self.add_token_without_literal(TokenType::StarStar);
+                } else if self.does_match('=') {
+                    self.add_token_without_literal(TokenType::StarEqual);
+                } else {
+                    self.add_token_without_literal(TokenType::Star);
+                }
+            }
 
             '/' => {
                 if self.does_match('/') {
                     self.comment();
                 } else if self.does_match('*') {
                     self.multiline_comment()?;
+                } else if self.does_match('=') {
+                    self.add_token_without_literal(TokenType::SlashEqual);
                 } else {
                     self.add_token_without_literal(TokenType::Slash);
                 }
             }
 
+            '~' => {
+                if self.does_match('/') {
+                    self.add_token_without_literal(TokenType::TildeSlash);
+                } else {
+                    self.add_token_without_literal(TokenType::Tilde);
+                }
+            }
+
+            '&' => self.add_token_without_literal(TokenType::Ampersand),
+
+            '|' => self.add_token_without_literal(TokenType::Pipe),
+
+            '^' => self.add_token_without_literal(TokenType::Caret),
+
             '!' => {
                 if self.does_match('=') {
                     self.add_token_without_literal(TokenType::BangEqual);
@@ -99,6 +166,8 @@ impl Scanner {
             '=' => {
                 if self.does_match('=') {
                     self.add_token_without_literal(TokenType::EqualEqual);
+                } else if self.does_match('>') {
+                    self.add_token_without_literal(TokenType::FatArrow);
                 } else {
                     self.add_token_without_literal(TokenType::Equal);
                 }
@@ -107,6 +176,8 @@ impl Scanner {
             '<' => {
                 if self.does_match('=') {
                     self.add_token_without_literal(TokenType::LessEqual);
+                } else if self.does_match('<') {
+                    self.add_token_without_literal(TokenType::LessLess);
                 } else {
                     self.add_token_without_literal(TokenType::Less);
                 }
@@ -115,6 +186,8 @@ impl Scanner {
             '>' => {
                 if self.does_match('=') {
                     self.add_token_without_literal(TokenType::GreaterEqual);
+                } else if self.does_match('>') {
+                    self.add_token_without_literal(TokenType::GreaterGreater);
                 } else {
                     self.add_token_without_literal(TokenType::Greater);
                 }
@@ -154,7 +227,7 @@ impl Scanner {
             return '\0';
         }
 
-        return self.source_as_vec[self.current] as char;
+        self.source_as_vec[self.current] as char
     }
 
     fn peek_next(&self) -> char {
@@ -162,7 +235,22 @@ impl Scanner {
             return '\0';
         }
 
-        return self.source_as_vec[self.current + 1] as char;
+        self.source_as_vec[self.current + 1] as char
+    }
+
+    // Whether the last token ends an operand that is not a name.
+    fn follows_value(&self) -> bool {
+        matches!(
+            self.tokens.last().map(|token| &token.ttype),
+            Some(
+                TokenType::Number
+                    | TokenType::String
+                    | TokenType::True
+                    | TokenType::False
+                    | TokenType::Nil
+                    | TokenType::RightParen
+            )
+        )
     }
 
     fn does_match(&mut self, expected: char) -> bool {
@@ -172,17 +260,15 @@ impl Scanner {
 
         self.current += 1;
 
-        return true;
+        true
     }
 
     fn is_digit(&self, charecter: char) -> bool {
-        charecter >= '0' && charecter <= '9'
+        charecter.is_ascii_digit()
     }
 
     fn is_alpha(&self, charecter: char) -> bool {
-        charecter >= 'a' && charecter <= 'z'
-            || charecter >= 'A' && charecter <= 'Z'
-            || charecter == '_'
+        charecter.is_ascii_alphabetic() || charecter == '_'
     }
 
     fn is_alpha_numeric(&self, charecter: char) -> bool {
@@ -276,25 +362,38 @@ impl Scanner {
 
         self.advance(); // covering up the ending qoute
 
-        self.add_token(
-            TokenType::String,
-            Object::String(self.source[self.start..self.current].to_string()),
-        );
+        // The lexeme keeps its quotes; the value is only what is between them.
+        let literal = self.constant(self.start + 1, self.current - 1);
+        self.add_token(TokenType::String, Object::String(literal));
 
         Ok(())
     }
 
+    fn constant(&mut self, start: usize, end: usize) -> Handle {
+        let literal = &self.source[start..end];
+        if let Some(handle) = self.constants.get(literal) {
+            return *handle;
+        }
+
+        let handle = heap::constant_string(literal.to_string());
+        self.constants.insert(literal.to_string(), handle);
+        handle
+    }
+
     fn add_token_without_literal(&mut self, token_type: TokenType) {
         self.add_token(token_type, Object::Nil);
     }
 
+    // Interned text lives as long as the thread does, so only names and
+    // punctuation are interned. A literal's value is its literal; its lexeme
+    // would be one more string that is never freed.
     fn add_token(&mut self, token_type: TokenType, literal: Object) {
-        self.tokens.push(Token::new(
-            token_type,
-            self.source[self.start..self.current].to_string(),
-            literal,
-            self.line,
-        ));
+        let lexeme = match token_type {
+            TokenType::String | TokenType::Number => Symbol::intern(""),
+            _ => Symbol::intern(&self.source[self.start..self.current]),
+        };
+        self.tokens
+            .push(Token::new(token_type, lexeme, literal, self.line));
     }
 
     fn init_keywords(&mut self) {
@@ -308,6 +407,8 @@ impl Scanner {
         self.keywords.insert(String::from("fun"), TokenType::Fun);
         self.keywords.insert(String::from("nil"), TokenType::Nil);
         self.keywords.insert(String::from("if"), TokenType::If);
+        self.keywords
+            .insert(String::from("match"), TokenType::Match);
         self.keywords.insert(String::from("or"), TokenType::Or);
         self.keywords
             .insert(String::from("print"), TokenType::Print);
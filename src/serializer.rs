@@ -0,0 +1,797 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Error, ErrorType},
+    expression::{
+        AssignmentExpression, BinaryExpression, CallExpression, Expression, ExpressionVisitor,
+        GetExpression, GroupingExpression, LiteralExpression, NilCoalescingExpression,
+        TernaryExpression, UnaryExpression, UpdateExpression, VariableExpression,
+    },
+    heap::{self, Handle},
+    interner::Symbol,
+    limits::Limits,
+    object::Object,
+    resolver::Resolver,
+    statement::{
+        BlockStatement, ExpressionStatement, FunctionStatement, IfStatement, MatchArm,
+        MatchStatement, PrintStatement, ReturnStatement, Statement, StatementVisitor,
+        VariableStatement,
+    },
+    token::Token,
+    token_type::TokenType,
+};
+
+// Layout of a `.loxc` file, all integers little-endian:
+//
+//   magic            b"LOXC"
+//   version          u16
+//   string count     u32, then every string as a u32 length and UTF-8 bytes
+//   statement count  u32, then every statement as a tree of tagged nodes
+//   checksum         u64, FNV-1a over everything before it
+//
+// Lexemes, identifiers and string literals are stored once in the string table
+// and referred to by index; every token keeps its source line.
+pub const MAGIC: &[u8; 4] = b"LOXC";
+pub const FORMAT_VERSION: u16 = 6;
+
+// The position of a token type in this table is its tag in the file, so new
+// token types go at the end and reordering requires a new format version.
+const TOKEN_TYPES: [TokenType; 59] = [
+    TokenType::LeftParen,
+    TokenType::RightParen,
+    TokenType::LeftBrace,
+    TokenType::RightBrace,
+    TokenType::Semicolon,
+    TokenType::Comma,
+    TokenType::Dot,
+    TokenType::Colon,
+    TokenType::Question,
+    TokenType::QuestionQuestion,
+    TokenType::Plus,
+    TokenType::Minus,
+    TokenType::Star,
+    TokenType::Slash,
+    TokenType::PlusEqual,
+    TokenType::MinusEqual,
+    TokenType::StarEqual,
+    TokenType::SlashEqual,
+    TokenType::PlusPlus,
+    TokenType::MinusMinus,
+    TokenType::Percent,
+    TokenType::StarStar,
+    TokenType::TildeSlash,
+    TokenType::Ampersand,
+    TokenType::Pipe,
+    TokenType::Caret,
+    TokenType::Tilde,
+    TokenType::Bang,
+    TokenType::BangEqual,
+    TokenType::Equal,
+    TokenType::EqualEqual,
+    TokenType::FatArrow,
+    TokenType::Greater,
+    TokenType::GreaterEqual,
+    TokenType::Less,
+    TokenType::LessEqual,
+    TokenType::LessLess,
+    TokenType::GreaterGreater,
+    TokenType::String,
+    TokenType::Number,
+    TokenType::Identifier,
+    TokenType::And,
+    TokenType::Class,
+    TokenType::Else,
+    TokenType::False,
+    TokenType::For,
+    TokenType::Fun,
+    TokenType::Nil,
+    TokenType::If,
+    TokenType::Match,
+    TokenType::Or,
+    TokenType::Print,
+    TokenType::Return,
+    TokenType::Super,
+    TokenType::This,
+    TokenType::True,
+    TokenType::Var,
+    TokenType::While,
+    TokenType::EOF,
+];
+
+const STATEMENT_VARIABLE: u8 = 0;
+const STATEMENT_EXPRESSION: u8 = 1;
+const STATEMENT_PRINT: u8 = 2;
+const STATEMENT_BLOCK: u8 = 3;
+const STATEMENT_IF: u8 = 4;
+const STATEMENT_MATCH: u8 = 5;
+const STATEMENT_FUNCTION: u8 = 6;
+const STATEMENT_RETURN: u8 = 7;
+
+const EXPRESSION_LITERAL: u8 = 0;
+const EXPRESSION_UNARY: u8 = 1;
+const EXPRESSION_BINARY: u8 = 2;
+const EXPRESSION_GROUPING: u8 = 3;
+const EXPRESSION_VARIABLE: u8 = 4;
+const EXPRESSION_TERNARY: u8 = 5;
+const EXPRESSION_NIL_COALESCING: u8 = 6;
+const EXPRESSION_ASSIGNMENT: u8 = 7;
+const EXPRESSION_UPDATE: u8 = 8;
+const EXPRESSION_CALL: u8 = 9;
+const EXPRESSION_GET: u8 = 10;
+
+const OBJECT_NIL: u8 = 0;
+const OBJECT_TRUE: u8 = 1;
+const OBJECT_FALSE: u8 = 2;
+const OBJECT_NUMBER: u8 = 3;
+const OBJECT_STRING: u8 = 4;
+
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn format_error(message: &str) -> Error {
+    Error::new(None, ErrorType::FormatError, message)
+}
+
+pub struct Serializer {
+    strings: Vec<String>,
+    string_indices: HashMap<String, u32>,
+    body: Vec<u8>,
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self {
+            strings: Vec::new(),
+            string_indices: HashMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn serialize(mut self, statements: &[Statement]) -> Result<Vec<u8>, Error> {
+        self.write_u32(statements.len() as u32);
+        for statement in statements {
+            statement.accept(&mut self)?;
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.strings.len() as u32).to_le_bytes());
+        for string in &self.strings {
+            bytes.extend_from_slice(&(string.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(string.as_bytes());
+        }
+        bytes.extend_from_slice(&self.body);
+        bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+
+        Ok(bytes)
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.body.push(value);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.body.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_string(&mut self, string: &str) {
+        let index = match self.string_indices.get(string) {
+            Some(index) => *index,
+            None => {
+                let index = self.strings.len() as u32;
+                self.strings.push(string.to_string());
+                self.string_indices.insert(string.to_string(), index);
+                index
+            }
+        };
+        self.write_u32(index);
+    }
+
+    fn write_object(&mut self, object: &Object) {
+        match object {
+            Object::Nil => self.write_u8(OBJECT_NIL),
+            Object::Bool(true) => self.write_u8(OBJECT_TRUE),
+            Object::Bool(false) => self.write_u8(OBJECT_FALSE),
+            Object::Number(number) => {
+                self.write_u8(OBJECT_NUMBER);
+                self.body.extend_from_slice(&number.to_le_bytes());
+            }
+            Object::String(handle) => {
+                self.write_u8(OBJECT_STRING);
+                heap::with_string(*handle, |string| self.write_string(string));
+            }
+            Object::Native(_) => unreachable!("native functions are never literals"),
+            Object::Foreign(_) => unreachable!("foreign objects are never literals"),
+            Object::Function(_) => unreachable!("functions are never literals"),
+        }
+    }
+
+    fn write_token(&mut self, token: &Token) {
+        let tag = TOKEN_TYPES
+            .iter()
+            .position(|ttype| ttype == &token.ttype)
+            .unwrap_or(TOKEN_TYPES.len() - 1);
+        self.write_u8(tag as u8);
+        self.write_string(token.lexeme.as_str());
+        self.write_object(&token.literal);
+        self.write_u32(token.line as u32);
+    }
+}
+
+impl StatementVisitor<()> for Serializer {
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_EXPRESSION);
+        statement.expression.accept(self)
+    }
+
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_PRINT);
+        self.write_token(&statement.keyword);
+        statement.expression.accept(self)
+    }
+
+    fn visit_variable_statement(&mut self, statement: &VariableStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_VARIABLE);
+        self.write_token(&statement.identifier);
+        statement.initializer.accept(self)
+    }
+
+    fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_BLOCK);
+        self.write_u32(statement.statements.len() as u32);
+        for statement in &statement.statements {
+            statement.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_IF);
+        statement.conditional.accept(self)?;
+        statement.then_branch.accept(self)?;
+        match &statement.else_branch {
+            Some(else_branch) => {
+                self.write_u8(1);
+                else_branch.accept(self)
+            }
+            None => {
+                self.write_u8(0);
+                Ok(())
+            }
+        }
+    }
+
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_MATCH);
+        statement.scrutinee.accept(self)?;
+        self.write_u32(statement.arms.len() as u32);
+        for arm in &statement.arms {
+            self.write_u32(arm.patterns.len() as u32);
+            for pattern in &arm.patterns {
+                pattern.accept(self)?;
+            }
+            arm.body.accept(self)?;
+        }
+        match &statement.default_branch {
+            Some(default_branch) => {
+                self.write_u8(1);
+                default_branch.accept(self)
+            }
+            None => {
+                self.write_u8(0);
+                Ok(())
+            }
+        }
+    }
+
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_FUNCTION);
+        self.write_token(&statement.name);
+        self.write_u32(statement.parameters.len() as u32);
+        for parameter in &statement.parameters {
+            self.write_token(parameter);
+        }
+        self.write_u32(statement.body.len() as u32);
+        for statement in statement.body.iter() {
+            statement.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<(), Error> {
+        self.write_u8(STATEMENT_RETURN);
+        self.write_token(&statement.keyword);
+        statement.value.accept(self)
+    }
+}
+
+impl ExpressionVisitor<()> for Serializer {
+    fn visit_literal_expression(&mut self, expression: &LiteralExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_LITERAL);
+        self.write_object(&expression.value);
+        Ok(())
+    }
+
+    fn visit_unary_expression(&mut self, expression: &UnaryExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_UNARY);
+        self.write_token(&expression.operator);
+        expression.right.accept(self)
+    }
+
+    fn visit_binary_expression(&mut self, expression: &BinaryExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_BINARY);
+        expression.left.accept(self)?;
+        self.write_token(&expression.operator);
+        expression.right.accept(self)
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &GroupingExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_GROUPING);
+        expression.expressions.accept(self)
+    }
+
+    fn visit_variable_expression(&mut self, expression: &VariableExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_VARIABLE);
+        self.write_token(&expression.identifier);
+        Ok(())
+    }
+
+    fn visit_ternary_expression(&mut self, expression: &TernaryExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_TERNARY);
+        expression.conditional.accept(self)?;
+        expression.then_branch.accept(self)?;
+        expression.else_branch.accept(self)
+    }
+
+    fn visit_nil_coalescing_expression(
+        &mut self,
+        expression: &NilCoalescingExpression,
+    ) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_NIL_COALESCING);
+        expression.left.accept(self)?;
+        self.write_token(&expression.operator);
+        expression.right.accept(self)
+    }
+
+    fn visit_assignment_expression(
+        &mut self,
+        expression: &AssignmentExpression,
+    ) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_ASSIGNMENT);
+        self.write_token(&expression.identifier);
+        self.write_token(&expression.operator);
+        expression.value.accept(self)
+    }
+
+    fn visit_update_expression(&mut self, expression: &UpdateExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_UPDATE);
+        self.write_token(&expression.identifier);
+        self.write_token(&expression.operator);
+        self.write_u8(expression.prefix as u8);
+        Ok(())
+    }
+
+    fn visit_call_expression(&mut self, expression: &CallExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_CALL);
+        expression.callee.accept(self)?;
+        self.write_token(&expression.paren);
+        self.write_u32(expression.arguments.len() as u32);
+        for argument in &expression.arguments {
+            argument.accept(self)?;
+        }
+        Ok(())
+    }
+
+    fn visit_get_expression(&mut self, expression: &GetExpression) -> Result<(), Error> {
+        self.write_u8(EXPRESSION_GET);
+        expression.object.accept(self)?;
+        self.write_token(&expression.name);
+        Ok(())
+    }
+}
+
+pub struct Deserializer<'a> {
+    bytes: &'a [u8],
+    current: usize,
+    strings: Vec<String>,
+    // Equal string literals share one pinned heap string.
+    constants: HashMap<String, Handle>,
+    max_depth: Option<usize>,
+}
+
+impl<'a> Deserializer<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_limits(bytes, Limits::default())
+    }
+
+    // A file nests as deeply as the `Parser` allows source to, and no deeper,
+    // whatever its checksum says.
+    pub fn with_limits(bytes: &'a [u8], limits: Limits) -> Self {
+        Self {
+            bytes,
+            current: 0,
+            strings: Vec::new(),
+            constants: HashMap::new(),
+            max_depth: limits.max_depth,
+        }
+    }
+
+    pub fn deserialize(mut self) -> Result<Vec<Statement>, Error> {
+        if !self.bytes.starts_with(MAGIC) {
+            return Err(format_error("Not a compiled rlox file"));
+        }
+        self.current = MAGIC.len();
+
+        let version = u16::from_le_bytes([self.read_u8()?, self.read_u8()?]);
+        if version != FORMAT_VERSION {
+            return Err(format_error(&format!(
+                "Unsupported compiled file version {} (expected {}), recompile the script",
+                version, FORMAT_VERSION
+            )));
+        }
+
+        let body_end = self
+            .bytes
+            .len()
+            .checked_sub(8)
+            .ok_or_else(|| format_error("Unexpected end of compiled file"))?;
+        let mut stored = [0; 8];
+        stored.copy_from_slice(&self.bytes[body_end..]);
+        if u64::from_le_bytes(stored) != checksum(&self.bytes[..body_end]) {
+            return Err(format_error(
+                "Checksum mismatch, the compiled file is corrupted",
+            ));
+        }
+        self.bytes = &self.bytes[..body_end];
+
+        let string_count = self.read_u32()?;
+        for _ in 0..string_count {
+            let length = self.read_u32()? as usize;
+            let bytes = self.read_bytes(length)?;
+            let string = String::from_utf8(bytes.to_vec())
+                .map_err(|_| format_error("Invalid string in compiled file"))?;
+            self.strings.push(string);
+        }
+
+        let statement_count = self.read_u32()?;
+        let mut statements = Vec::new();
+        for _ in 0..statement_count {
+            statements.push(self.read_statement(0)?);
+        }
+
+        if self.current != self.bytes.len() {
+            return Err(format_error("Trailing data in compiled file"));
+        }
+
+        // Variable bindings are not stored, so work them out again.
+        Resolver::new().resolve(&statements)?;
+        Ok(statements)
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let end = self.current + length;
+        if end > self.bytes.len() {
+            return Err(format_error("Unexpected end of compiled file"));
+        }
+        let bytes = &self.bytes[self.current..end];
+        self.current = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn peek_u8(&self) -> Result<u8, Error> {
+        self.bytes
+            .get(self.current)
+            .copied()
+            .ok_or_else(|| format_error("Unexpected end of compiled file"))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let index = self.read_u32()? as usize;
+        self.strings
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format_error("Invalid string index in compiled file"))
+    }
+
+    fn read_object(&mut self) -> Result<Object, Error> {
+        match self.read_u8()? {
+            OBJECT_NIL => Ok(Object::Nil),
+            OBJECT_TRUE => Ok(Object::Bool(true)),
+            OBJECT_FALSE => Ok(Object::Bool(false)),
+            OBJECT_NUMBER => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(self.read_bytes(8)?);
+                Ok(Object::Number(f64::from_le_bytes(bytes)))
+            }
+            OBJECT_STRING => {
+                let string = self.read_string()?;
+                let handle = *self
+                    .constants
+                    .entry(string)
+                    .or_insert_with_key(|string| heap::constant_string(string.clone()));
+                Ok(Object::String(handle))
+            }
+            _ => Err(format_error("Invalid value in compiled file")),
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Token, Error> {
+        let ttype = TOKEN_TYPES
+            .get(self.read_u8()? as usize)
+            .cloned()
+            .ok_or_else(|| format_error("Invalid token in compiled file"))?;
+        let lexeme = Symbol::intern(&self.read_string()?);
+        let literal = self.read_object()?;
+        let line = self.read_u32()? as usize;
+        Ok(Token::new(ttype, lexeme, literal, line))
+    }
+
+    // Statements and expressions nest where the `Parser` counts a level, with
+    // `depth` the levels around the one being read.
+    fn enter(&self, depth: usize) -> Result<usize, Error> {
+        match self.max_depth {
+            Some(max_depth) if depth >= max_depth => Err(format_error(&format!(
+                "Nesting exceeds the maximum depth of {} in compiled file",
+                max_depth
+            ))),
+            _ => Ok(depth + 1),
+        }
+    }
+
+    fn read_optional_statement(&mut self, depth: usize) -> Result<Option<Statement>, Error> {
+        match self.read_u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.read_statement(depth)?)),
+        }
+    }
+
+    fn read_statements(&mut self, depth: usize) -> Result<Vec<Statement>, Error> {
+        let count = self.read_u32()?;
+        let mut statements = Vec::new();
+        for _ in 0..count {
+            statements.push(self.read_statement(depth)?);
+        }
+        Ok(statements)
+    }
+
+    fn read_statement(&mut self, depth: usize) -> Result<Statement, Error> {
+        let tag = self.read_u8()?;
+
+        // A variable declaration is the one statement that is not a level of
+        // its own.
+        if tag == STATEMENT_VARIABLE {
+            let identifier = self.read_token()?;
+            let initializer = self.nested_expression(depth)?;
+            return Ok(Statement::VariableStatement(VariableStatement::new(
+                identifier,
+                Some(initializer),
+            )));
+        }
+
+        let depth = self.enter(depth)?;
+        match tag {
+            STATEMENT_EXPRESSION => Ok(Statement::ExpressionStatement(ExpressionStatement::new(
+                self.nested_expression(depth)?,
+            ))),
+
+            STATEMENT_PRINT => {
+                let keyword = self.read_token()?;
+                let expression = self.nested_expression(depth)?;
+                Ok(Statement::PrintStatement(PrintStatement::new(
+                    keyword, expression,
+                )))
+            }
+
+            STATEMENT_BLOCK => Ok(Statement::BlockStatement(BlockStatement::new(
+                self.read_statements(depth)?,
+            ))),
+
+            STATEMENT_IF => {
+                let conditional = self.nested_expression(depth)?;
+                let then_branch = self.read_statement(depth)?;
+                let else_branch = self.read_optional_statement(depth)?;
+                Ok(Statement::IfStatement(IfStatement::new(
+                    conditional,
+                    then_branch,
+                    else_branch,
+                )))
+            }
+
+            STATEMENT_MATCH => {
+                let scrutinee = self.nested_expression(depth)?;
+                let arm_count = self.read_u32()?;
+                let mut arms = Vec::new();
+                for _ in 0..arm_count {
+                    let pattern_count = self.read_u32()?;
+                    let mut patterns = Vec::new();
+                    for _ in 0..pattern_count {
+                        patterns.push(self.nested_expression(depth)?);
+                    }
+                    let body = self.read_statement(depth)?;
+                    arms.push(MatchArm::new(patterns, body));
+                }
+                let default_branch = self.read_optional_statement(depth)?;
+                Ok(Statement::MatchStatement(MatchStatement::new(
+                    scrutinee,
+                    arms,
+                    default_branch,
+                )))
+            }
+
+            STATEMENT_FUNCTION => {
+                let name = self.read_token()?;
+                let parameter_count = self.read_u32()?;
+                let mut parameters = Vec::new();
+                for _ in 0..parameter_count {
+                    parameters.push(self.read_token()?);
+                }
+                let body = self.read_statements(depth)?;
+                Ok(Statement::FunctionStatement(FunctionStatement::new(
+                    name, parameters, body,
+                )))
+            }
+
+            STATEMENT_RETURN => {
+                let keyword = self.read_token()?;
+                let value = self.nested_expression(depth)?;
+                Ok(Statement::ReturnStatement(ReturnStatement::new(
+                    keyword,
+                    Some(value),
+                )))
+            }
+
+            _ => Err(format_error("Invalid statement in compiled file")),
+        }
+    }
+
+    fn nested_expression(&mut self, depth: usize) -> Result<Expression, Error> {
+        let depth = self.enter(depth)?;
+        self.read_expression(depth)
+    }
+
+    fn read_expression(&mut self, depth: usize) -> Result<Expression, Error> {
+        if matches!(
+            self.peek_u8()?,
+            EXPRESSION_BINARY
+                | EXPRESSION_NIL_COALESCING
+                | EXPRESSION_TERNARY
+                | EXPRESSION_CALL
+                | EXPRESSION_GET
+        ) {
+            return self.read_chain(depth);
+        }
+
+        match self.read_u8()? {
+            EXPRESSION_LITERAL => Ok(Expression::Literal(LiteralExpression::new(
+                self.read_object()?,
+            ))),
+
+            EXPRESSION_UNARY => {
+                let operator = self.read_token()?;
+                let right = self.nested_expression(depth)?;
+                Ok(Expression::Unary(UnaryExpression::new(operator, right)))
+            }
+
+            EXPRESSION_GROUPING => Ok(Expression::Grouping(GroupingExpression::new(
+                self.nested_expression(depth)?,
+            ))),
+
+            EXPRESSION_VARIABLE => Ok(Expression::VariableExpression(VariableExpression::new(
+                self.read_token()?,
+            ))),
+
+            EXPRESSION_ASSIGNMENT => {
+                let identifier = self.read_token()?;
+                let operator = self.read_token()?;
+                let value = self.nested_expression(depth)?;
+                Ok(Expression::Assignment(AssignmentExpression::new(
+                    identifier, operator, value,
+                )))
+            }
+
+            EXPRESSION_UPDATE => {
+                let identifier = self.read_token()?;
+                let operator = self.read_token()?;
+                let prefix = self.read_u8()? != 0;
+                Ok(Expression::Update(UpdateExpression::new(
+                    identifier, operator, prefix,
+                )))
+            }
+
+            _ => Err(format_error("Invalid expression in compiled file")),
+        }
+    }
+
+    // Operators, conditionals, calls and property accesses are written with
+    // the operand on their left first, so `1 + 2 + 3` starts with two binary
+    // tags. Such a chain is read in a loop, innermost operand first, as the
+    // `Parser` reads it; only a call or property access in a row of them is a
+    // level deeper than the one before.
+    fn read_chain(&mut self, depth: usize) -> Result<Expression, Error> {
+        let mut tags = Vec::new();
+        while matches!(
+            self.peek_u8()?,
+            EXPRESSION_BINARY
+                | EXPRESSION_NIL_COALESCING
+                | EXPRESSION_TERNARY
+                | EXPRESSION_CALL
+                | EXPRESSION_GET
+        ) {
+            tags.push(self.read_u8()?);
+        }
+
+        let mut expression = self.read_expression(depth)?;
+        let mut calls = 0;
+        for tag in tags.into_iter().rev() {
+            if !matches!(tag, EXPRESSION_CALL | EXPRESSION_GET) {
+                calls = 0;
+            }
+
+            expression = match tag {
+                EXPRESSION_BINARY => {
+                    let operator = self.read_token()?;
+                    let right = self.nested_expression(depth)?;
+                    Expression::Binary(BinaryExpression::new(expression, operator, right))
+                }
+
+                EXPRESSION_NIL_COALESCING => {
+                    let operator = self.read_token()?;
+                    let right = self.nested_expression(depth)?;
+                    Expression::NilCoalescing(NilCoalescingExpression::new(
+                        expression, operator, right,
+                    ))
+                }
+
+                EXPRESSION_TERNARY => {
+                    let then_branch = self.nested_expression(depth)?;
+                    let else_branch = self.nested_expression(depth)?;
+                    Expression::Ternary(TernaryExpression::new(
+                        expression,
+                        then_branch,
+                        else_branch,
+                    ))
+                }
+
+                EXPRESSION_CALL => {
+                    let depth = self.enter(depth + calls)?;
+                    calls += 1;
+                    let paren = self.read_token()?;
+                    let count = self.read_u32()?;
+                    let mut arguments = Vec::new();
+                    for _ in 0..count {
+                        arguments.push(self.nested_expression(depth)?);
+                    }
+                    Expression::Call(CallExpression::new(expression, paren, arguments))
+                }
+
+                _ => {
+                    self.enter(depth + calls)?;
+                    calls += 1;
+                    let name = self.read_token()?;
+                    Expression::Get(GetExpression::new(expression, name))
+                }
+            };
+        }
+        Ok(expression)
+    }
+}
@@ -1,19 +1,26 @@
+use std::{cell::Cell, rc::Rc};
+
 use crate::error::Error;
-use crate::expression::{Expression, LiteralExpression};
+use crate::expression::{Binding, Expression, LiteralExpression};
 use crate::object::Object;
 use crate::token::Token;
 
+#[allow(clippy::enum_variant_names)]
 pub enum Statement {
     VariableStatement(VariableStatement),
     ExpressionStatement(ExpressionStatement),
     PrintStatement(PrintStatement),
     BlockStatement(BlockStatement),
     IfStatement(IfStatement),
+    MatchStatement(MatchStatement),
+    FunctionStatement(FunctionStatement),
+    ReturnStatement(ReturnStatement),
 }
 
 pub struct VariableStatement {
     pub identifier: Token,
     pub initializer: Expression,
+    pub binding: Cell<Binding>,
 }
 
 pub struct ExpressionStatement {
@@ -31,9 +38,35 @@ pub struct IfStatement {
 }
 
 pub struct PrintStatement {
+    pub keyword: Token,
     pub expression: Expression,
 }
 
+pub struct MatchStatement {
+    pub scrutinee: Expression,
+    pub arms: Vec<MatchArm>,
+    pub default_branch: Option<Box<Statement>>,
+}
+
+pub struct MatchArm {
+    pub patterns: Vec<Expression>,
+    pub body: Statement,
+}
+
+pub struct FunctionStatement {
+    pub name: Token,
+    pub parameters: Vec<Token>,
+    // Shared with the functions created from this declaration, which can
+    // outlive the statements it was parsed into.
+    pub body: Rc<[Statement]>,
+    pub binding: Cell<Binding>,
+}
+
+pub struct ReturnStatement {
+    pub keyword: Token,
+    pub value: Expression,
+}
+
 impl ExpressionStatement {
     pub fn new(expression: Expression) -> Self {
         Self { expression }
@@ -41,8 +74,11 @@ impl ExpressionStatement {
 }
 
 impl PrintStatement {
-    pub fn new(expression: Expression) -> Self {
-        Self { expression }
+    pub fn new(keyword: Token, expression: Expression) -> Self {
+        Self {
+            keyword,
+            expression,
+        }
     }
 }
 
@@ -54,16 +90,22 @@ impl Statement {
             Self::PrintStatement(statement) => statement.accept(visitor),
             Self::BlockStatement(statement) => statement.accept(visitor),
             Self::IfStatement(statement) => statement.accept(visitor),
+            Self::MatchStatement(statement) => statement.accept(visitor),
+            Self::FunctionStatement(statement) => statement.accept(visitor),
+            Self::ReturnStatement(statement) => statement.accept(visitor),
         }
     }
 }
 
 pub trait StatementVisitor<T> {
-    fn visit_expression_statement(&self, statement: &ExpressionStatement) -> Result<T, Error>;
-    fn visit_print_statement(&self, statement: &PrintStatement) -> Result<T, Error>;
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) -> Result<T, Error>;
+    fn visit_print_statement(&mut self, statement: &PrintStatement) -> Result<T, Error>;
     fn visit_variable_statement(&mut self, statement: &VariableStatement) -> Result<T, Error>;
     fn visit_block_statement(&mut self, statement: &BlockStatement) -> Result<T, Error>;
     fn visit_if_statement(&mut self, statement: &IfStatement) -> Result<T, Error>;
+    fn visit_match_statement(&mut self, statement: &MatchStatement) -> Result<T, Error>;
+    fn visit_function_statement(&mut self, statement: &FunctionStatement) -> Result<T, Error>;
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) -> Result<T, Error>;
 }
 
 impl VariableStatement {
@@ -72,6 +114,7 @@ impl VariableStatement {
             identifier,
             initializer: initializer
                 .unwrap_or_else(|| Expression::Literal(LiteralExpression::new(Object::Nil))),
+            binding: Cell::new(Binding::Global),
         }
     }
 
@@ -99,11 +142,7 @@ impl IfStatement {
         Self {
             conditional,
             then_branch: Box::new(then_branch),
-            else_branch: if let Some(else_branch) = else_branch {
-                Some(Box::new(else_branch))
-            } else {
-                None
-            },
+            else_branch: else_branch.map(Box::new),
         }
     }
 
@@ -112,14 +151,67 @@ impl IfStatement {
     }
 }
 
+impl MatchStatement {
+    pub fn new(
+        scrutinee: Expression,
+        arms: Vec<MatchArm>,
+        default_branch: Option<Statement>,
+    ) -> Self {
+        Self {
+            scrutinee,
+            arms,
+            default_branch: default_branch.map(Box::new),
+        }
+    }
+
+    fn accept<T>(&self, visitor: &mut dyn StatementVisitor<T>) -> Result<T, Error> {
+        visitor.visit_match_statement(self)
+    }
+}
+
+impl MatchArm {
+    pub fn new(patterns: Vec<Expression>, body: Statement) -> Self {
+        Self { patterns, body }
+    }
+}
+
+impl FunctionStatement {
+    pub fn new(name: Token, parameters: Vec<Token>, body: Vec<Statement>) -> Self {
+        Self {
+            name,
+            parameters,
+            body: body.into(),
+            binding: Cell::new(Binding::Global),
+        }
+    }
+
+    fn accept<T>(&self, visitor: &mut dyn StatementVisitor<T>) -> Result<T, Error> {
+        visitor.visit_function_statement(self)
+    }
+}
+
+impl ReturnStatement {
+    pub fn new(keyword: Token, value: Option<Expression>) -> Self {
+        Self {
+            keyword,
+            value: value
+                .unwrap_or_else(|| Expression::Literal(LiteralExpression::new(Object::Nil))),
+        }
+    }
+
+    fn accept<T>(&self, visitor: &mut dyn StatementVisitor<T>) -> Result<T, Error> {
+        visitor.visit_return_statement(self)
+    }
+}
+
 impl ExpressionStatement {
-    fn accept<T>(&self, visitor: &dyn StatementVisitor<T>) -> Result<T, Error> {
+    fn accept<T>(&self, visitor: &mut dyn StatementVisitor<T>) -> Result<T, Error> {
         visitor.visit_expression_statement(self)
     }
 }
 
 impl PrintStatement {
-    fn accept<T>(&self, visitor: &dyn StatementVisitor<T>) -> Result<T, Error> {
+    fn accept<T>(&self, visitor: &mut dyn StatementVisitor<T>) -> Result<T, Error> {
         visitor.visit_print_statement(self)
     }
 }
@@ -1,27 +1,32 @@
 use core::fmt;
 
-use crate::{object::Object, token_type::TokenType};
+use crate::{interner::Symbol, object::Object, token_type::TokenType};
 
 #[derive(Clone)]
 pub struct Token {
     pub ttype: TokenType,
-    pub lexeme: String,
+    // Empty for string and number tokens, whose text is their `literal`.
+    pub lexeme: Symbol,
     pub literal: Object,
     pub line: usize,
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lexeme = match self.ttype {
+            TokenType::String | TokenType::Number => self.literal.to_source(),
+            _ => self.lexeme.to_string(),
+        };
         write!(
             f,
             "Token: '{}' of type '{}' (Object: {}) in line {}",
-            self.lexeme, self.ttype, self.literal, self.line
+            lexeme, self.ttype, self.literal, self.line
         )
     }
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: String, literal: Object, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: Symbol, literal: Object, line: usize) -> Self {
         Self {
             ttype: token_type,
             lexeme,
@@ -1,5 +1,6 @@
 use std::fmt;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, PartialEq)]
 pub enum TokenType {
     LeftParen,
@@ -9,19 +10,38 @@ pub enum TokenType {
     Semicolon,
     Comma,
     Dot,
+    Colon,
+    Question,
+    QuestionQuestion,
     Plus,
     Minus,
     Star,
     Slash,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PlusPlus,
+    MinusMinus,
+    Percent,
+    StarStar,
+    TildeSlash,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
 
     String,
     Number,
@@ -35,6 +55,7 @@ pub enum TokenType {
     Fun,
     Nil,
     If,
+    Match,
     Or,
     Print,
     Return,
@@ -57,18 +78,37 @@ impl fmt::Display for TokenType {
             TokenType::Semicolon => write!(f, "semicolon"),
             TokenType::Comma => write!(f, "comma"),
             TokenType::Dot => write!(f, "dot"),
+            TokenType::Colon => write!(f, "colon"),
+            TokenType::Question => write!(f, "question"),
+            TokenType::QuestionQuestion => write!(f, "question_question"),
             TokenType::Plus => write!(f, "plus"),
             TokenType::Minus => write!(f, "minus"),
             TokenType::Star => write!(f, "star"),
             TokenType::Slash => write!(f, "slash"),
+            TokenType::PlusEqual => write!(f, "plus_equal"),
+            TokenType::MinusEqual => write!(f, "minus_equal"),
+            TokenType::StarEqual => write!(f, "star_equal"),
+            TokenType::SlashEqual => write!(f, "slash_equal"),
+            TokenType::PlusPlus => write!(f, "plus_plus"),
+            TokenType::MinusMinus => write!(f, "minus_minus"),
+            TokenType::Percent => write!(f, "percent"),
+            TokenType::StarStar => write!(f, "star_star"),
+            TokenType::TildeSlash => write!(f, "tilde_slash"),
+            TokenType::Ampersand => write!(f, "ampersand"),
+            TokenType::Pipe => write!(f, "pipe"),
+            TokenType::Caret => write!(f, "caret"),
+            TokenType::Tilde => write!(f, "tilde"),
             TokenType::Bang => write!(f, "bang"),
             TokenType::BangEqual => write!(f, "bang_equal"),
             TokenType::Equal => write!(f, "equal"),
             TokenType::EqualEqual => write!(f, "equal_equal"),
+            TokenType::FatArrow => write!(f, "fat_arrow"),
             TokenType::Greater => write!(f, "greater"),
             TokenType::GreaterEqual => write!(f, "greater_equal"),
             TokenType::Less => write!(f, "less"),
             TokenType::LessEqual => write!(f, "less_equal"),
+            TokenType::LessLess => write!(f, "less_less"),
+            TokenType::GreaterGreater => write!(f, "greater_greater"),
             TokenType::String => write!(f, "string"),
             TokenType::Number => write!(f, "number"),
             TokenType::Identifier => write!(f, "identifier"),
@@ -80,6 +120,7 @@ impl fmt::Display for TokenType {
             TokenType::Fun => write!(f, "fun"),
             TokenType::Nil => write!(f, "nil"),
             TokenType::If => write!(f, "if"),
+            TokenType::Match => write!(f, "match"),
             TokenType::Or => write!(f, "or"),
             TokenType::Print => write!(f, "print"),
             TokenType::Return => write!(f, "return"),
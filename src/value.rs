@@ -0,0 +1,261 @@
+use std::{fmt, rc::Rc};
+
+use crate::{
+    error::Error,
+    foreign::{Foreign, ForeignObject},
+    function::LoxFunction,
+    heap,
+    native::NativeFunction,
+    object::Object,
+};
+
+// A Lox value as the host sees it. Unlike `Object`, it owns its string, so it
+// stays valid no matter what the collector frees afterwards.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Native(Rc<NativeFunction>),
+    Foreign(Rc<ForeignObject>),
+    Function(LoxFunction),
+    Nil,
+}
+
+impl From<Object> for Value {
+    fn from(object: Object) -> Self {
+        match object {
+            Object::Number(number) => Self::Number(number),
+            Object::String(handle) => Self::String(heap::with_string(handle, str::to_string)),
+            Object::Bool(boolean) => Self::Bool(boolean),
+            Object::Native(handle) => Self::Native(heap::native(handle)),
+            Object::Foreign(handle) => Self::Foreign(heap::foreign(handle)),
+            Object::Function(handle) => Self::Function(LoxFunction::new(handle)),
+            Object::Nil => Self::Nil,
+        }
+    }
+}
+
+// Strings, natives and foreign objects are copied into a new, unrooted heap
+// object, which the caller has to store somewhere the collector can see before
+// the next safepoint. A script function is still on the heap and is used as is.
+// Copying fails while a script runs if it would exceed the memory limit.
+impl TryFrom<Value> for Object {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        Ok(match value {
+            Value::Number(number) => Self::Number(number),
+            Value::String(string) => Self::String(heap::allocate_string(string)?),
+            Value::Bool(boolean) => Self::Bool(boolean),
+            Value::Native(function) => Self::Native(heap::allocate_native(function)?),
+            Value::Foreign(object) => Self::Foreign(heap::allocate_foreign(object)?),
+            Value::Function(function) => Self::Function(function.handle()),
+            Value::Nil => Self::Nil,
+        })
+    }
+}
+
+impl Object {
+    // The host hands values over between runs, when there is no memory limit.
+    pub(crate) fn from_host(value: Value) -> Self {
+        Self::try_from(value).expect("no memory limit between runs")
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}", number),
+            Self::String(string) => write!(f, "{}", string),
+            Self::Bool(boolean) => write!(f, "{}", boolean),
+            Self::Native(function) => write!(f, "{:?}", function),
+            Self::Foreign(object) => write!(f, "{}", object),
+            Self::Function(function) => write!(f, "{:?}", function),
+            Self::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+impl Value {
+    pub fn foreign<T: Foreign>(value: T) -> Self {
+        Self::Foreign(Rc::new(ForeignObject::new(value)))
+    }
+
+    // How the value reads in an error message, such as "a number".
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::Number(_) => "a number",
+            Self::String(_) => "a string",
+            Self::Bool(_) => "a boolean",
+            Self::Native(_) | Self::Function(_) => "a function",
+            Self::Foreign(_) => "a foreign object",
+            Self::Nil => "nil",
+        }
+    }
+}
+
+fn expected(expected: &str, value: &Value) -> Error {
+    Error::runtime(&format!(
+        "Expected {} but got {}",
+        expected,
+        value.describe()
+    ))
+}
+
+impl From<f64> for Value {
+    fn from(number: f64) -> Self {
+        Self::Number(number)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Self {
+        Self::Bool(boolean)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Self::String(string.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Self::String(string)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Nil, Into::into)
+    }
+}
+
+// Conversions back work on borrowed values, which is what a native function
+// receives, and on owned ones.
+impl TryFrom<&Value> for f64 {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Number(number) => Ok(*number),
+            _ => Err(expected("a number", value)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Bool(boolean) => Ok(*boolean),
+            _ => Err(expected("a boolean", value)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::String(string) => Ok(string.clone()),
+            _ => Err(expected("a string", value)),
+        }
+    }
+}
+
+impl<'a, T: TryFrom<&'a Value, Error = Error>> TryFrom<&'a Value> for Option<T> {
+    type Error = Error;
+
+    fn try_from(value: &'a Value) -> Result<Self, Error> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        Self::try_from(&value)
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        Self::try_from(&value)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::String(string) => Ok(string),
+            value => Err(expected("a string", &value)),
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = Error>> TryFrom<Value> for Option<T> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
+}
+
+// Converts the arguments of a native function into a tuple of Rust values in
+// one go, as in `let (name, count) = <(String, f64)>::from_arguments(arguments)?;`.
+pub trait FromArguments: Sized {
+    fn from_arguments(arguments: &[Value]) -> Result<Self, Error>;
+}
+
+macro_rules! impl_from_arguments {
+    ($count:literal; $($element:ident $index:tt),*) => {
+        impl<$($element),*> FromArguments for ($($element,)*)
+        where
+            $($element: for<'a> TryFrom<&'a Value, Error = Error>,)*
+        {
+            fn from_arguments(arguments: &[Value]) -> Result<Self, Error> {
+                if arguments.len() != $count {
+                    return Err(Error::runtime(&format!(
+                        "Expected {} arguments but got {}",
+                        $count,
+                        arguments.len()
+                    )));
+                }
+
+                Ok(($(argument::<$element>(arguments, $index)?,)*))
+            }
+        }
+    };
+}
+
+fn argument<T: for<'a> TryFrom<&'a Value, Error = Error>>(
+    arguments: &[Value],
+    index: usize,
+) -> Result<T, Error> {
+    T::try_from(&arguments[index])
+        .map_err(|error| Error::runtime(&format!("Argument {}: {}", index + 1, error.message())))
+}
+
+impl_from_arguments!(0;);
+impl_from_arguments!(1; A 0);
+impl_from_arguments!(2; A 0, B 1);
+impl_from_arguments!(3; A 0, B 1, C 2);
+impl_from_arguments!(4; A 0, B 1, C 2, D 3);
+impl_from_arguments!(5; A 0, B 1, C 2, D 3, E 4);
+impl_from_arguments!(6; A 0, B 1, C 2, D 3, E 4, F 5);
@@ -0,0 +1,463 @@
+use std::{collections::HashMap, io::Write, rc::Rc};
+
+use crate::{
+    builtins,
+    chunk::{Capture, Chunk, OpCode},
+    config::InterpreterConfig,
+    error::{Error, ErrorType},
+    foreign,
+    function::{self, Code, Function},
+    heap::{self, Handle, Retained, Upvalue},
+    interner::Symbol,
+    limits::Meter,
+    native::{self, NativeFunction},
+    object::Object,
+    operation::{self, Output},
+    token_type::TokenType,
+    value::Value,
+};
+
+#[cfg(feature = "nan-boxing")]
+use crate::nan_box::NanBox;
+
+// With the `nan-boxing` feature the stack holds 8-byte NaN-boxed values
+// instead of full `Object`s; everything else in the VM works on `Object`.
+#[cfg(feature = "nan-boxing")]
+type StackValue = NanBox;
+#[cfg(not(feature = "nan-boxing"))]
+type StackValue = Object;
+
+#[cfg(feature = "nan-boxing")]
+fn pack(object: Object) -> StackValue {
+    NanBox::from(object)
+}
+
+#[cfg(not(feature = "nan-boxing"))]
+fn pack(object: Object) -> StackValue {
+    object
+}
+
+#[cfg(feature = "nan-boxing")]
+fn unpack(value: StackValue) -> Object {
+    Object::from(value)
+}
+
+#[cfg(not(feature = "nan-boxing"))]
+fn unpack(value: StackValue) -> Object {
+    value
+}
+
+// Where a caller resumes once the function it called returns. The script
+// itself runs without a function. Locals of a call live on the stack from
+// `base` on, just above the function being called.
+struct CallFrame {
+    function: Option<Rc<Function>>,
+    ip: usize,
+    base: usize,
+}
+
+pub struct VirtualMachine {
+    stack: Vec<StackValue>,
+    globals: HashMap<Symbol, Object>,
+    frames: Vec<CallFrame>,
+    // Upvalues still pointing into the stack, with the slot they point to.
+    open_upvalues: Vec<(usize, Handle)>,
+    output: Output,
+    meter: Meter,
+    // As in `Interpreter`, what the globals referred to after the last run.
+    retained: Retained,
+}
+
+impl Default for VirtualMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VirtualMachine {
+    pub fn new() -> Self {
+        Self::with_config(InterpreterConfig::default())
+    }
+
+    // The same configuration an `Interpreter` takes, with the same builtins.
+    pub fn with_config(config: InterpreterConfig) -> Self {
+        let mut vm = Self {
+            stack: Vec::new(),
+            globals: HashMap::new(),
+            frames: Vec::new(),
+            open_upvalues: Vec::new(),
+            output: Output::new(config.print),
+            meter: Meter::new(config.limits),
+            retained: Retained::default(),
+        };
+        for native in builtins::natives(&config) {
+            vm.define_native(native);
+        }
+        vm
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output.redirect(output);
+    }
+
+    // As `Interpreter::set_global`.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.define_global(Symbol::intern(name), Object::from_host(value));
+    }
+
+    fn define_native(&mut self, native: NativeFunction) {
+        let name = Symbol::intern(native.name());
+        self.define_global(name, Object::from_host(Value::Native(Rc::new(native))));
+    }
+
+    fn define_global(&mut self, name: Symbol, value: Object) {
+        self.globals.insert(name, value);
+        self.retain_globals();
+    }
+
+    pub fn interpret(&mut self, script: &Chunk) -> Result<(), Error> {
+        let memory_limit = self.meter.start();
+        let result = self.run(script);
+        self.meter.finish(memory_limit);
+        self.output.finish(&result);
+
+        self.retain_globals();
+        result
+    }
+
+    fn retain_globals(&mut self) {
+        self.retained
+            .replace(self.globals.values().filter_map(Object::handle));
+    }
+
+    fn run(&mut self, script: &Chunk) -> Result<(), Error> {
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+        let mut function: Option<Rc<Function>> = None;
+        let mut ip = 0;
+        let mut base = 0;
+
+        loop {
+            let chunk = match function.as_deref() {
+                Some(function) => compiled_chunk(function),
+                None => script,
+            };
+
+            // Every instruction boundary is a safepoint: all live values are on
+            // the stack or in globals.
+            if heap::collection_due() {
+                heap::collect(self.roots());
+            }
+
+            let offset = ip;
+            self.meter
+                .step(None)
+                .map_err(|error| error.at_line(Some(chunk.line(offset))))?;
+            let opcode = OpCode::from_byte(chunk.code[ip]).ok_or_else(|| {
+                Error::new(
+                    chunk.line(offset),
+                    ErrorType::RuntimeError,
+                    "Invalid instruction",
+                )
+            })?;
+            ip += 1;
+
+            match opcode {
+                OpCode::Constant => {
+                    let index = read_operand(chunk, &mut ip);
+                    self.push(chunk.constants[index]);
+                }
+
+                OpCode::Nil => self.push(Object::Nil),
+
+                OpCode::True => self.push(Object::Bool(true)),
+
+                OpCode::False => self.push(Object::Bool(false)),
+
+                OpCode::Pop => {
+                    self.pop();
+                }
+
+                OpCode::Dup => self.push(self.peek()),
+
+                OpCode::DefineGlobal => {
+                    let name = read_name(chunk, &mut ip);
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+
+                OpCode::GetGlobal => {
+                    let name = read_name(chunk, &mut ip);
+                    let value = self
+                        .globals
+                        .get(&name)
+                        .copied()
+                        .ok_or_else(|| undefined_variable(chunk.line(offset), name))?;
+                    self.push(value);
+                }
+
+                OpCode::SetGlobal => {
+                    let name = read_name(chunk, &mut ip);
+                    let value = self.peek();
+                    match self.globals.get_mut(&name) {
+                        Some(slot) => *slot = value,
+                        None => return Err(undefined_variable(chunk.line(offset), name)),
+                    }
+                }
+
+                OpCode::GetLocal => {
+                    let slot = read_operand(chunk, &mut ip);
+                    self.stack.push(self.stack[base + slot]);
+                }
+
+                OpCode::SetLocal => {
+                    let slot = read_operand(chunk, &mut ip);
+                    self.stack[base + slot] = pack(self.peek());
+                }
+
+                OpCode::GetUpvalue => {
+                    let index = read_operand(chunk, &mut ip);
+                    let value = match heap::upvalue(captured(&function, index)) {
+                        Upvalue::Open(slot) => unpack(self.stack[slot]),
+                        Upvalue::Closed(value) => value,
+                    };
+                    self.push(value);
+                }
+
+                OpCode::SetUpvalue => {
+                    let index = read_operand(chunk, &mut ip);
+                    let upvalue = captured(&function, index);
+                    match heap::upvalue(upvalue) {
+                        Upvalue::Open(slot) => self.stack[slot] = pack(self.peek()),
+                        Upvalue::Closed(_) => {
+                            heap::set_upvalue(upvalue, Upvalue::Closed(self.peek()))
+                        }
+                    }
+                }
+
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+
+                OpCode::Add => self.binary(TokenType::Plus, chunk.line(offset))?,
+                OpCode::Subtract => self.binary(TokenType::Minus, chunk.line(offset))?,
+                OpCode::Multiply => self.binary(TokenType::Star, chunk.line(offset))?,
+                OpCode::Divide => self.binary(TokenType::Slash, chunk.line(offset))?,
+                OpCode::Modulo => self.binary(TokenType::Percent, chunk.line(offset))?,
+                OpCode::Power => self.binary(TokenType::StarStar, chunk.line(offset))?,
+                OpCode::FloorDivide => self.binary(TokenType::TildeSlash, chunk.line(offset))?,
+                OpCode::BitwiseAnd => self.binary(TokenType::Ampersand, chunk.line(offset))?,
+                OpCode::BitwiseOr => self.binary(TokenType::Pipe, chunk.line(offset))?,
+                OpCode::BitwiseXor => self.binary(TokenType::Caret, chunk.line(offset))?,
+                OpCode::ShiftLeft => self.binary(TokenType::LessLess, chunk.line(offset))?,
+                OpCode::ShiftRight => self.binary(TokenType::GreaterGreater, chunk.line(offset))?,
+                OpCode::Equal => self.binary(TokenType::EqualEqual, chunk.line(offset))?,
+                OpCode::NotEqual => self.binary(TokenType::BangEqual, chunk.line(offset))?,
+                OpCode::Greater => self.binary(TokenType::Greater, chunk.line(offset))?,
+                OpCode::GreaterEqual => self.binary(TokenType::GreaterEqual, chunk.line(offset))?,
+                OpCode::Less => self.binary(TokenType::Less, chunk.line(offset))?,
+                OpCode::LessEqual => self.binary(TokenType::LessEqual, chunk.line(offset))?,
+
+                OpCode::Negate => self.unary(TokenType::Minus, chunk.line(offset))?,
+                OpCode::Not => self.unary(TokenType::Bang, chunk.line(offset))?,
+                OpCode::BitwiseNot => self.unary(TokenType::Tilde, chunk.line(offset))?,
+
+                OpCode::Print => {
+                    let value = self.pop();
+                    self.output.print(value, chunk.line(offset))?;
+                }
+
+                OpCode::Call => {
+                    let count = read_operand(chunk, &mut ip);
+                    let start = self.stack.len() - count;
+
+                    // The callee stays below its arguments for the whole call.
+                    if let Some(callee) = bytecode_function(unpack(self.stack[start - 1])) {
+                        let line = chunk.line(offset);
+                        function::check_arity(callee.arity, count, Some(line))?;
+                        function::check_depth(self.frames.len(), Some(line))?;
+                        self.frames.push(CallFrame {
+                            function: function.replace(callee),
+                            ip,
+                            base,
+                        });
+                        ip = 0;
+                        base = start;
+                        continue;
+                    }
+
+                    let arguments: Vec<Object> = self.stack.drain(start..).map(unpack).collect();
+                    let callee = self.pop();
+                    self.push(native::call(callee, &arguments, Some(chunk.line(offset)))?);
+                }
+
+                OpCode::GetProperty => {
+                    let name = read_name(chunk, &mut ip);
+                    let object = self.pop();
+                    self.push(foreign::get_property(object, name, chunk.line(offset))?);
+                }
+
+                OpCode::Closure => {
+                    let index = read_operand(chunk, &mut ip);
+                    let compiled = Rc::clone(&chunk.functions[index]);
+                    let line = chunk.line(offset);
+                    let captured = compiled
+                        .captures
+                        .iter()
+                        .map(|capture| match *capture {
+                            Capture::Local(slot) => self.capture_upvalue(base + slot as usize),
+                            Capture::Upvalue(index) => Ok(captured(&function, index as usize)),
+                        })
+                        .collect::<Result<_, Error>>()
+                        .map_err(|error| error.at_line(Some(line)))?;
+                    let handle = heap::allocate_function(Rc::new(Function {
+                        name: compiled.name,
+                        arity: compiled.arity,
+                        code: Code::Bytecode(compiled),
+                        captured,
+                    }))
+                    .map_err(|error| error.at_line(Some(line)))?;
+                    self.push(Object::Function(handle));
+                }
+
+                OpCode::Jump => {
+                    let jump = read_operand(chunk, &mut ip);
+                    ip += jump;
+                }
+
+                OpCode::JumpIfFalse => {
+                    let jump = read_operand(chunk, &mut ip);
+                    if !operation::is_truthy(&self.peek()) {
+                        ip += jump;
+                    }
+                }
+
+                OpCode::JumpIfNotNil => {
+                    let jump = read_operand(chunk, &mut ip);
+                    if !matches!(self.peek(), Object::Nil) {
+                        ip += jump;
+                    }
+                }
+
+                OpCode::Return => {
+                    let value = self.pop();
+                    // Returning from the script itself ends the program.
+                    let Some(caller) = self.frames.pop() else {
+                        return Ok(());
+                    };
+                    self.close_upvalues(base);
+                    self.stack.truncate(base - 1);
+                    self.push(value);
+                    function = caller.function;
+                    ip = caller.ip;
+                    base = caller.base;
+                }
+            }
+        }
+    }
+
+    fn binary(&mut self, operator: TokenType, line: usize) -> Result<(), Error> {
+        let right = self.pop();
+        let left = self.pop();
+        self.push(operation::binary(&operator, line, left, right)?);
+        Ok(())
+    }
+
+    fn unary(&mut self, operator: TokenType, line: usize) -> Result<(), Error> {
+        let right = self.pop();
+        self.push(operation::unary(&operator, line, right)?);
+        Ok(())
+    }
+
+    // Several closures capturing the same variable share one upvalue.
+    fn capture_upvalue(&mut self, slot: usize) -> Result<Handle, Error> {
+        if let Some((_, upvalue)) = self.open_upvalues.iter().find(|(open, _)| *open == slot) {
+            return Ok(*upvalue);
+        }
+
+        let upvalue = heap::allocate_upvalue(slot)?;
+        self.open_upvalues.push((slot, upvalue));
+        Ok(upvalue)
+    }
+
+    // Moves the variables from `from` up off the stack, which is about to drop
+    // them, into the upvalues that still refer to them.
+    fn close_upvalues(&mut self, from: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|(slot, upvalue)| {
+            if *slot < from {
+                return true;
+            }
+            heap::set_upvalue(*upvalue, Upvalue::Closed(unpack(stack[*slot])));
+            false
+        });
+    }
+
+    fn roots(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.stack
+            .iter()
+            .map(|value| unpack(*value))
+            .chain(self.globals.values().copied())
+            .filter_map(|object| object.handle())
+            .chain(self.open_upvalues.iter().map(|(_, upvalue)| *upvalue))
+    }
+
+    fn push(&mut self, object: Object) {
+        self.stack.push(pack(object));
+    }
+
+    fn pop(&mut self) -> Object {
+        self.stack.pop().map(unpack).unwrap_or(Object::Nil)
+    }
+
+    fn peek(&self) -> Object {
+        self.stack
+            .last()
+            .copied()
+            .map(unpack)
+            .unwrap_or(Object::Nil)
+    }
+}
+
+fn bytecode_function(callee: Object) -> Option<Rc<Function>> {
+    let Object::Function(handle) = callee else {
+        return None;
+    };
+    let function = heap::function(handle);
+    matches!(function.code, Code::Bytecode(_)).then_some(function)
+}
+
+fn compiled_chunk(function: &Function) -> &Chunk {
+    match &function.code {
+        Code::Bytecode(compiled) => &compiled.chunk,
+        _ => unreachable!("only compiled functions run on the VM"),
+    }
+}
+
+// Only code inside a function refers to upvalues.
+fn captured(function: &Option<Rc<Function>>, index: usize) -> Handle {
+    function
+        .as_ref()
+        .expect("upvalue used outside of a function")
+        .captured[index]
+}
+
+fn read_operand(chunk: &Chunk, ip: &mut usize) -> usize {
+    let operand = u16::from_be_bytes([chunk.code[*ip], chunk.code[*ip + 1]]);
+    *ip += 2;
+    operand as usize
+}
+
+fn read_name(chunk: &Chunk, ip: &mut usize) -> Symbol {
+    chunk.names[read_operand(chunk, ip)]
+}
+
+fn undefined_variable(line: usize, name: Symbol) -> Error {
+    Error::new(
+        line,
+        ErrorType::RuntimeError,
+        &format!("Undefined variable {}", name),
+    )
+}
@@ -0,0 +1,51 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+
+// Counts every allocation. This file holds a single test, so nothing else
+// allocates while it measures.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn operators_do_not_allocate() {
+    let mut source = String::from("var flag = true;\nvar total = nil;\n");
+    for index in 0..5_000 {
+        source.push_str(&format!(
+            "flag = !flag == ({index} < 2500) ? false : true;\ntotal = (total ?? 0) + {index};\n"
+        ));
+    }
+    let tokens = Scanner::new(&source).scan_tokens().unwrap();
+    let statements = Parser::new(tokens).parse().unwrap();
+    let mut interpreter = Interpreter::new();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    interpreter.interpret(&statements).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    // A few for the interpreter's own tables, none per operator.
+    assert!(allocations < 100, "{} allocations", allocations);
+}
@@ -0,0 +1,216 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::Duration;
+
+use rlox::compiler::Compiler;
+use rlox::config::{Capability, InterpreterConfig};
+use rlox::error::{Error, ErrorType};
+use rlox::heap;
+use rlox::interpreter::Interpreter;
+use rlox::ir_interpreter::IrInterpreter;
+use rlox::limits::Limits;
+use rlox::lowering::Lowerer;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::vm::VirtualMachine;
+
+const BACKENDS: [&str; 3] = ["tree", "vm", "ir"];
+
+// Takes far longer than any limit in these tests, without nesting deeply.
+const ENDLESS: &str = "fun f(n) { if (n == 0) return 0; return f(n - 1) + f(n - 1); } f(60);";
+
+// One instance of a backend, which keeps its globals from run to run.
+enum Backend {
+    Tree(Interpreter),
+    Vm(VirtualMachine),
+    Ir(IrInterpreter),
+}
+
+impl Backend {
+    fn new(backend: &str, config: InterpreterConfig) -> Self {
+        match backend {
+            "tree" => Self::Tree(Interpreter::with_config(config)),
+            "vm" => Self::Vm(VirtualMachine::with_config(config)),
+            _ => Self::Ir(IrInterpreter::with_config(config)),
+        }
+    }
+
+    fn set_output(&mut self, output: Box<dyn Write>) {
+        match self {
+            Self::Tree(interpreter) => interpreter.set_output(output),
+            Self::Vm(vm) => vm.set_output(output),
+            Self::Ir(interpreter) => interpreter.set_output(output),
+        }
+    }
+
+    fn run(&mut self, source: &str) -> Result<(), Error> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        match self {
+            Self::Tree(interpreter) => interpreter.interpret(&statements),
+            Self::Vm(vm) => vm.interpret(&Compiler::new().compile(&statements)?),
+            Self::Ir(interpreter) => interpreter.interpret(&Lowerer::new().lower(&statements)?),
+        }
+    }
+}
+
+fn run(backend: &str, config: InterpreterConfig, source: &str) -> Result<(), Error> {
+    Backend::new(backend, config).run(source)
+}
+
+// Collects what a script prints.
+#[derive(Clone, Default)]
+struct Printed(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Printed {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Printed {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+fn printed(backend: &str, source: &str) -> String {
+    let printed = Printed::default();
+    let mut instance = Backend::new(backend, InterpreterConfig::default());
+    instance.set_output(Box::new(printed.clone()));
+    assert!(instance.run(source).is_ok(), "{}: {}", backend, source);
+    printed.text()
+}
+
+fn failure(backend: &str, config: InterpreterConfig, source: &str) -> Error {
+    match run(backend, config, source) {
+        Ok(()) => panic!("{}: {} should fail", backend, source),
+        Err(error) => error,
+    }
+}
+
+#[test]
+fn every_backend_has_the_builtins() {
+    for backend in BACKENDS {
+        let config = InterpreterConfig::trusted();
+        assert!(run(backend, config.clone(), "var now = clock() + random();").is_ok());
+
+        let exit = failure(backend, config, "exit(3);");
+        assert_eq!(exit.exit_status(), Some(3), "{}", backend);
+    }
+}
+
+#[test]
+fn every_backend_enforces_capabilities() {
+    for backend in BACKENDS {
+        let config = InterpreterConfig::trusted().deny(Capability::Clock);
+        let error = failure(backend, config, "\nclock();");
+        assert_eq!(error.etype(), ErrorType::PermissionError, "{}", backend);
+        assert_eq!(
+            error.to_string(),
+            "[line 2] PermissionError: clock requires the clock capability"
+        );
+
+        let error = failure(backend, InterpreterConfig::new().print(false), "\nprint 1;");
+        assert_eq!(
+            error.to_string(),
+            "[line 2] PermissionError: print is not allowed",
+            "{}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn every_backend_enforces_limits() {
+    for backend in BACKENDS {
+        let limits = Limits {
+            max_steps: Some(1_000),
+            ..Limits::default()
+        };
+        let config = InterpreterConfig::new().limits(limits);
+        let error = failure(backend, config, ENDLESS);
+        assert_eq!(error.etype(), ErrorType::LimitError, "{}", backend);
+        assert_eq!(error.message(), "Execution exceeds the limit of 1000 steps");
+
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(20)),
+            ..Limits::default()
+        };
+        let config = InterpreterConfig::new().limits(limits);
+        let error = failure(backend, config, ENDLESS);
+        assert_eq!(error.etype(), ErrorType::LimitError, "{}", backend);
+        assert_eq!(error.message(), "Execution exceeds the time limit of 20 ms");
+    }
+}
+
+// Each call keeps a new closure alive, so the chain only grows.
+fn chain(length: usize) -> String {
+    format!(
+        "fun f(n, next) {{ fun link() {{ return next; }} if (n == 0) return link; return f(n - 1, link); }} var chain = f({}, nil);",
+        length
+    )
+}
+
+#[test]
+fn every_backend_limits_memory() {
+    for backend in BACKENDS {
+        let limits = Limits {
+            max_memory: Some(16 * 1024),
+            ..Limits::default()
+        };
+        let config = InterpreterConfig::new().limits(limits);
+        assert!(
+            run(backend, config.clone(), &chain(5)).is_ok(),
+            "{}",
+            backend
+        );
+
+        let error = failure(backend, config, &chain(500));
+        assert_eq!(error.etype(), ErrorType::MemoryError, "{}", backend);
+        assert_eq!(error.message(), "Out of memory");
+    }
+}
+
+#[test]
+fn every_backend_compares_strings() {
+    let source = r#"
+        print "a" < "a";
+        print "a" <= "a";
+        print "a" > "a";
+        print "a" >= "a";
+        print "a" <= "b";
+        print "b" <= "a";
+    "#;
+    for backend in BACKENDS {
+        assert_eq!(
+            printed(backend, source),
+            "false\ntrue\nfalse\ntrue\ntrue\nfalse\n",
+            "{}",
+            backend
+        );
+    }
+}
+
+// Another backend on the same thread may collect between two runs.
+#[test]
+fn every_backend_keeps_its_globals_between_runs() {
+    for backend in BACKENDS {
+        let printed = Printed::default();
+        let mut instance = Backend::new(backend, InterpreterConfig::default());
+        instance.set_output(Box::new(printed.clone()));
+
+        instance
+            .run("var greeting = \"hi\" + \"!\"; fun greet() { return greeting; }")
+            .unwrap();
+        heap::collect([]);
+        instance.run("print greet();").unwrap();
+        assert_eq!(printed.text(), "hi!\n", "{}", backend);
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Write},
+    process,
+    rc::Rc,
+};
+
+use rlox::config::{Capability, InterpreterConfig};
+use rlox::error::ErrorType;
+use rlox::{Lox, Value};
+
+fn trusted() -> Lox {
+    Lox::with_config(InterpreterConfig::trusted())
+}
+
+#[test]
+fn file_builtins_take_literal_paths() {
+    let path = env::temp_dir().join(format!("rlox-builtins-{}.txt", process::id()));
+    let path = path.to_str().unwrap();
+    let mut lox = trusted();
+
+    lox.run(&format!("write_file(\"{}\", \"written by lox\");", path))
+        .unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "written by lox");
+    assert_eq!(
+        lox.eval_expr(&format!("read_file(\"{}\")", path)).unwrap(),
+        Value::from("written by lox")
+    );
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn getenv_takes_a_literal_name() {
+    env::set_var("RLOX_BUILTINS_TEST", "set");
+    let mut lox = trusted();
+
+    assert_eq!(
+        lox.eval_expr("getenv(\"RLOX_BUILTINS_TEST\")").unwrap(),
+        Value::from("set")
+    );
+    assert_eq!(
+        lox.eval_expr("getenv(\"RLOX_BUILTINS_UNSET\")").unwrap(),
+        Value::Nil
+    );
+}
+
+#[test]
+fn builtins_need_their_capability() {
+    let calls = [
+        (Capability::Filesystem, "read_file(\"/etc/hostname\")"),
+        (Capability::Filesystem, "write_file(\"/tmp/denied\", \"\")"),
+        (Capability::Environment, "getenv(\"PATH\")"),
+        (Capability::Process, "exit(0)"),
+        (Capability::Clock, "clock()"),
+        (Capability::Random, "random()"),
+    ];
+    for (capability, call) in calls {
+        let mut lox = Lox::with_config(InterpreterConfig::trusted().deny(capability));
+        let error = lox.eval_expr(call).unwrap_err();
+        assert_eq!(error.etype(), ErrorType::PermissionError);
+        assert_eq!(
+            error.message(),
+            format!(
+                "{} requires the {} capability",
+                &call[..call.find('(').unwrap()],
+                capability
+            )
+        );
+    }
+
+    let mut lox = Lox::with_config(InterpreterConfig::new().allow(Capability::Environment));
+    assert!(lox.eval_expr("getenv(\"PATH\")").is_ok());
+    assert!(lox.eval_expr("read_file(\"/etc/hostname\")").is_err());
+}
+
+// A sink that only hands on what it was given when flushed.
+#[derive(Default)]
+struct Buffered {
+    pending: Vec<u8>,
+    flushed: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Write for Buffered {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.pending.write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed.borrow_mut().append(&mut self.pending);
+        Ok(())
+    }
+}
+
+#[test]
+fn exit_stops_the_script_not_the_host() {
+    let output = Buffered::default();
+    let flushed = output.flushed.clone();
+    let mut lox = trusted();
+    lox.set_output(Box::new(output));
+
+    let error = lox
+        .run("var before = 1; print before; fun stop() { exit(3); } stop(); var after = 2;")
+        .unwrap_err();
+    assert_eq!(error.etype(), ErrorType::Exit);
+    assert_eq!(error.exit_status(), Some(3));
+    assert_eq!(flushed.borrow().as_slice(), b"1\n");
+    assert_eq!(lox.get_global("before"), Some(Value::Number(1.0)));
+    assert_eq!(lox.get_global("after"), None);
+
+    assert_eq!(lox.eval_expr("before + 1").unwrap(), Value::Number(2.0));
+    assert_eq!(lox.run("print 1 & 1.5;").unwrap_err().exit_status(), None);
+}
@@ -0,0 +1,171 @@
+use std::{
+    env, fs,
+    io::Write,
+    process::{self, Command, Output, Stdio},
+};
+
+fn rlox(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(arguments)
+        .output()
+        .expect("rlox should start")
+}
+
+fn rlox_with_stdin(arguments: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("rlox should start");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn definitions_equal_string_literals() {
+    let output = rlox(&["-D", "mode=prod", "-e", "print mode == \"prod\";"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "true\n");
+}
+
+#[test]
+fn script_arguments_equal_string_literals() {
+    let output = rlox(&["-e", "print arg(0) == \"foo\"; print arg(1);", "foo", "bar"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "true\nbar\n");
+}
+
+#[test]
+fn every_backend_allows_the_same_call_depth() {
+    let recursion = "fun r(n) { if (n == 0) return 0; return r(n - 1) + 1; }";
+    for backend in [None, Some("--vm"), Some("--ir")] {
+        let run = |source: &str| {
+            let mut arguments: Vec<&str> = backend.into_iter().collect();
+            arguments.extend(["-e", source]);
+            rlox(&arguments)
+        };
+
+        let output = run(&format!("{} print r(1023);", recursion));
+        assert_eq!(stdout(&output), "1023\n", "{:?}", backend);
+
+        let output = run(&format!("{} print r(1024);", recursion));
+        assert_eq!(output.status.code(), Some(70), "{:?}", backend);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            "[line 1] RuntimeError: Stack overflow\n"
+        );
+    }
+}
+
+#[test]
+fn exit_codes_follow_the_phase_that_failed() {
+    let nested = format!("print {}1{};", "(".repeat(300), ")".repeat(300));
+    let output = rlox(&["-e", &nested]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "[line 1] LimitError: Nesting exceeds the maximum depth of 256\n"
+    );
+
+    assert_eq!(rlox(&["-e", "print ;"]).status.code(), Some(65));
+    assert_eq!(rlox(&["-e", "print 1 & 1.5;"]).status.code(), Some(70));
+    assert_eq!(
+        rlox(&["--vm", "-e", "print -nil();"]).status.code(),
+        Some(70)
+    );
+    assert_eq!(rlox(&["run", "missing.lox"]).status.code(), Some(66));
+    assert_eq!(rlox(&["--bogus"]).status.code(), Some(64));
+}
+
+#[test]
+fn long_operator_chains_run_on_every_backend() {
+    let sum = format!("print {};", vec!["1"; 10_000].join(" + "));
+    for backend in [None, Some("--vm"), Some("--ir")] {
+        let mut arguments: Vec<&str> = backend.into_iter().collect();
+        arguments.extend(["-e", &sum]);
+        assert_eq!(stdout(&rlox(&arguments)), "10000\n", "{:?}", backend);
+    }
+}
+
+#[test]
+fn corrupt_compiled_files_are_data_errors() {
+    let path = env::temp_dir().join(format!("rlox-corrupt-{}.loxc", process::id()));
+    let path = path.to_str().unwrap();
+    assert!(rlox(&["compile", "-e", "print 1;", "-o", path])
+        .status
+        .success());
+
+    let mut bytes = fs::read(path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0x01;
+    fs::write(path, bytes).unwrap();
+
+    let output = rlox(&["run", path]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "FormatError: Checksum mismatch, the compiled file is corrupted\n"
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn exit_ends_the_script_with_its_status() {
+    let output = rlox(&["-e", "print 1; fun stop() { exit(3); } stop(); print 2;"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), "1\n");
+    assert!(output.stderr.is_empty());
+
+    assert_eq!(rlox(&["-e", "exit(0); print 1;"]).status.code(), Some(0));
+}
+
+#[test]
+fn every_backend_runs_the_builtins() {
+    for backend in ["--vm", "--ir"] {
+        let output = rlox(&[backend, "-e", "print clock() > 0; exit(3);"]);
+        assert_eq!(output.status.code(), Some(3), "{}", backend);
+        assert_eq!(stdout(&output), "true\n", "{}", backend);
+    }
+}
+
+#[test]
+fn scripts_run_from_stdin() {
+    let script = "print 1 + 1;\n";
+    for arguments in [&[][..], &["-"], &["run", "-"], &["--vm", "-"]] {
+        let output = rlox_with_stdin(arguments, script);
+        assert!(output.status.success(), "{:?}", arguments);
+        assert_eq!(stdout(&output), "2\n", "{:?}", arguments);
+    }
+}
+
+#[test]
+fn only_a_first_line_shebang_is_skipped() {
+    let path = env::temp_dir().join(format!("rlox-shebang-{}.lox", process::id()));
+    fs::write(&path, "#!/usr/bin/env rlox\nprint 1;\n").unwrap();
+    let output = rlox(&[path.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1\n");
+    fs::remove_file(&path).unwrap();
+
+    let output = rlox_with_stdin(&["-"], "#!/usr/bin/env rlox\nprint 1;\n");
+    assert_eq!(stdout(&output), "1\n");
+
+    let output = rlox_with_stdin(&["-"], "print 1;\n#!/usr/bin/env rlox\n");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "[line 2] LexingError: Invalid charecter\n"
+    );
+}
@@ -0,0 +1,132 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+const BACKENDS: [&[&str]; 6] = [
+    &[],
+    &["--vm"],
+    &["--ir"],
+    &["--gc-stress"],
+    &["--vm", "--gc-stress"],
+    &["--ir", "--gc-stress"],
+];
+
+fn examples() -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("example");
+    let mut examples: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lox"))
+        .collect();
+    examples.sort();
+    examples
+}
+
+// The expected output of `example/<name>.lox` is `tests/golden/<name>.out`.
+fn golden(example: &Path) -> String {
+    let name = example.file_stem().unwrap().to_str().unwrap();
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.out", name));
+    fs::read_to_string(path).unwrap()
+}
+
+fn run(arguments: &[&str], script: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(arguments)
+        .arg(script)
+        .output()
+        .expect("rlox should start");
+    assert!(
+        output.status.success(),
+        "{} {:?}: {}",
+        script.display(),
+        arguments,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn examples_match_their_golden_output() {
+    for example in examples() {
+        let expected = golden(&example);
+        for backend in BACKENDS {
+            assert_eq!(
+                run(backend, &example),
+                expected,
+                "{} {:?}",
+                example.display(),
+                backend
+            );
+        }
+    }
+}
+
+// The golden output is what the unoptimized script prints.
+#[test]
+fn optimized_examples_match_their_golden_output() {
+    for example in examples() {
+        let expected = golden(&example);
+        for backend in BACKENDS {
+            let arguments: Vec<&str> = ["-O"].iter().chain(backend).copied().collect();
+            assert_eq!(
+                run(&arguments, &example),
+                expected,
+                "{} {:?}",
+                example.display(),
+                arguments
+            );
+        }
+    }
+}
+
+#[test]
+fn compiled_examples_match_their_golden_output() {
+    let compiled = env::temp_dir().join(format!("rlox-golden-{}.loxc", process::id()));
+    for example in examples() {
+        let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .arg("compile")
+            .arg(&example)
+            .arg("-o")
+            .arg(&compiled)
+            .output()
+            .expect("rlox should start");
+        assert!(output.status.success(), "{}", example.display());
+
+        let expected = golden(&example);
+        for backend in BACKENDS {
+            assert_eq!(
+                run(backend, &compiled),
+                expected,
+                "{} {:?}",
+                example.display(),
+                backend
+            );
+        }
+    }
+    fs::remove_file(compiled).unwrap();
+}
+
+// `tests/golden/ir/<name>.lox` lowers to `tests/golden/ir/<name>.out`.
+#[test]
+fn emitted_ir_matches_its_golden_output() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/ir");
+    for entry in fs::read_dir(directory).unwrap() {
+        let script = entry.unwrap().path();
+        if script
+            .extension()
+            .is_some_and(|extension| extension == "lox")
+        {
+            let expected = fs::read_to_string(script.with_extension("out")).unwrap();
+            assert_eq!(
+                run(&["--emit=ir"], &script),
+                expected,
+                "{}",
+                script.display()
+            );
+        }
+    }
+}
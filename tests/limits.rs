@@ -0,0 +1,45 @@
+use rlox::config::InterpreterConfig;
+use rlox::error::ErrorType;
+use rlox::limits::Limits;
+use rlox::{Lox, Value};
+
+#[test]
+fn operator_chains_are_not_nesting() {
+    let mut lox = Lox::new();
+
+    let sum = vec!["1"; 300].join(" + ");
+    assert_eq!(lox.eval_expr(&sum).unwrap(), Value::Number(300.0));
+
+    let fallback = format!("{} ?? 7", vec!["nil"; 300].join(" ?? "));
+    assert_eq!(lox.eval_expr(&fallback).unwrap(), Value::Number(7.0));
+}
+
+#[test]
+fn deep_nesting_is_a_limit_error() {
+    let limits = Limits {
+        max_depth: Some(32),
+        ..Limits::default()
+    };
+    let mut lox = Lox::with_config(InterpreterConfig::new().limits(limits));
+
+    let grouped = format!("{}1{}", "(".repeat(40), ")".repeat(40));
+    let error = lox.eval_expr(&grouped).unwrap_err();
+    assert_eq!(error.etype(), ErrorType::LimitError);
+    assert_eq!(error.message(), "Nesting exceeds the maximum depth of 32");
+
+    let sum = vec!["1"; 40].join(" + ");
+    assert_eq!(lox.eval_expr(&sum).unwrap(), Value::Number(40.0));
+}
+
+#[test]
+fn steps_are_limited() {
+    let limits = Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    };
+    let mut lox = Lox::with_config(InterpreterConfig::new().limits(limits));
+
+    let error = lox.run("fun f(n) { return f(n + 1); } f(0);").unwrap_err();
+    assert_eq!(error.etype(), ErrorType::LimitError);
+    assert_eq!(error.message(), "Execution exceeds the limit of 100 steps");
+}
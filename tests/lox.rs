@@ -0,0 +1,202 @@
+use std::cell::Cell;
+
+use rlox::config::InterpreterConfig;
+use rlox::error::ErrorType;
+use rlox::foreign::{Foreign, Methods};
+use rlox::{FromArguments, Lox, Value};
+
+#[test]
+fn strings_cross_the_api_unquoted() {
+    let mut lox = Lox::new();
+
+    assert_eq!(lox.eval_expr("\"h\"").unwrap(), Value::from("h"));
+
+    lox.set_global("host", Value::from("h"));
+    assert_eq!(lox.eval_expr("host == \"h\"").unwrap(), Value::Bool(true));
+
+    lox.run("var script = \"s\";").unwrap();
+    let script: String = lox.get_global("script").unwrap().try_into().unwrap();
+    assert_eq!(script, "s");
+}
+
+#[test]
+fn host_calls_report_no_line() {
+    let mut lox = Lox::new();
+
+    let error = lox.call("missing", &[]).unwrap_err();
+    assert_eq!(error.line(), None);
+    assert_eq!(
+        error.to_string(),
+        "RuntimeError: Undefined variable missing"
+    );
+
+    lox.run("fun two(a, b) { return a + b; }").unwrap();
+    let error = lox.call("two", &[Value::Number(1.0)]).unwrap_err();
+    assert_eq!(error.line(), None);
+
+    let error = lox.run("\nprint two(1);").unwrap_err();
+    assert_eq!(error.line(), Some(2));
+}
+
+#[test]
+fn decrement_is_only_read_where_it_can_apply() {
+    let mut lox = Lox::new();
+    lox.run("var x = 2; var a = 1; var b = 1; fun f() { return 5; }")
+        .unwrap();
+
+    assert_eq!(lox.eval_expr("1--1").unwrap(), Value::Number(2.0));
+    assert_eq!(lox.eval_expr("f()--1").unwrap(), Value::Number(6.0));
+    assert_eq!(lox.eval_expr("(1)--x").unwrap(), Value::Number(3.0));
+    assert_eq!(lox.eval_expr("++x ** 2").unwrap(), Value::Number(9.0));
+    assert_eq!(lox.eval_expr("x-- - --x").unwrap(), Value::Number(2.0));
+
+    // Before decrement these were a double negation and a subtraction.
+    let error = lox.run("print --1;").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 1] ParsingError: Invalid operand for '--'"
+    );
+    let error = lox.run("print a--b;").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 1] ParsingError: Expect ';' after value"
+    );
+
+    for source in ["x--1", "--1", "a--b", "++x++", "++(x)"] {
+        let error = lox.eval_expr(source).unwrap_err();
+        assert_eq!(error.etype(), ErrorType::ParsingError, "{}", source);
+    }
+}
+
+#[test]
+fn denied_print_reports_its_line() {
+    let mut lox = Lox::with_config(InterpreterConfig::new().print(false));
+
+    let error = lox.run("print false;").unwrap_err();
+    assert_eq!(error.etype(), ErrorType::PermissionError);
+    assert_eq!(error.line(), Some(1));
+}
+
+// Scripts can't assign properties, so a counter changes through its methods.
+struct Counter {
+    count: Cell<f64>,
+}
+
+impl Foreign for Counter {
+    fn type_name(&self) -> &str {
+        "Counter"
+    }
+
+    fn get(&self, property: &str) -> Option<Value> {
+        match property {
+            "count" => Some(Value::Number(self.count.get())),
+            _ => None,
+        }
+    }
+
+    fn methods(methods: &mut Methods<Self>) {
+        methods.add("add", 1, |counter, arguments| {
+            let (amount,): (f64,) = FromArguments::from_arguments(arguments)?;
+            counter.count.set(counter.count.get() + amount);
+            Ok(Value::Nil)
+        });
+    }
+}
+
+fn counter() -> Lox {
+    let mut lox = Lox::new();
+    lox.set_global(
+        "counter",
+        Value::foreign(Counter {
+            count: Cell::new(1.0),
+        }),
+    );
+    lox
+}
+
+#[test]
+fn foreign_properties_are_read_and_changed_through_methods() {
+    let mut lox = counter();
+
+    assert_eq!(lox.eval_expr("counter.count").unwrap(), Value::Number(1.0));
+    lox.run("counter.add(2); var add = counter.add; add(3);")
+        .unwrap();
+    assert_eq!(lox.eval_expr("counter.count").unwrap(), Value::Number(6.0));
+
+    let error = lox.run("counter.count = 2;").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 1] ParsingError: Invalid assignment target"
+    );
+}
+
+#[test]
+fn missing_foreign_members_are_runtime_errors() {
+    let mut lox = counter();
+
+    let error = lox.run("\nprint counter.size;").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 2] RuntimeError: Undefined property 'size' on Counter"
+    );
+
+    let error = lox.run("\ncounter.reset();").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 2] RuntimeError: Undefined property 'reset' on Counter"
+    );
+}
+
+#[test]
+fn foreign_method_arity_is_checked_at_the_call() {
+    let mut lox = counter();
+
+    let error = lox.run("var add = counter.add;\n\nadd(1, 2);").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 3] RuntimeError: Expected 1 arguments but got 2"
+    );
+}
+
+fn repeat() -> Lox {
+    let mut lox = Lox::new();
+    lox.register_fn("repeat", 2, |arguments| {
+        let (text, times): (String, f64) = FromArguments::from_arguments(arguments)?;
+        Ok(Value::from(text.repeat(times as usize)))
+    });
+    lox
+}
+
+#[test]
+fn typed_natives_convert_their_arguments() {
+    let mut lox = repeat();
+
+    assert_eq!(
+        lox.eval_expr("repeat(\"ab\", 2)").unwrap(),
+        Value::from("abab")
+    );
+
+    let error = lox.run("\nrepeat(\"ab\", \"2\");").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 2] RuntimeError: Argument 2: Expected a number but got a string"
+    );
+
+    let error = lox.run("\n\nrepeat(\"ab\");").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[line 3] RuntimeError: Expected 2 arguments but got 1"
+    );
+}
+
+#[test]
+fn argument_tuples_check_their_length() {
+    let arguments = [Value::from("ab")];
+    let error = <(String, f64)>::from_arguments(&arguments).unwrap_err();
+    assert_eq!(error.line(), None);
+    assert_eq!(error.message(), "Expected 2 arguments but got 1");
+
+    let arguments = [Value::Nil, Value::Number(1.0)];
+    let (text, number): (Option<String>, f64) = FromArguments::from_arguments(&arguments).unwrap();
+    assert_eq!((text, number), (None, 1.0));
+}
@@ -0,0 +1,67 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use rlox::compiler::Compiler;
+use rlox::interpreter::Interpreter;
+use rlox::ir_interpreter::IrInterpreter;
+use rlox::lowering::Lowerer;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::vm::VirtualMachine;
+use rlox::Statement;
+
+// A sink the test can read back after handing it to a backend.
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+const SOURCE: &str = "print \"out\"; print 1 + 2;";
+
+fn parse() -> Vec<Statement> {
+    let tokens = Scanner::new(SOURCE).scan_tokens().unwrap();
+    Parser::new(tokens).parse().unwrap()
+}
+
+#[test]
+fn every_backend_prints_to_its_output() {
+    let statements = parse();
+
+    let buffer = Buffer::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(buffer.clone()));
+    interpreter.interpret(&statements).unwrap();
+    assert_eq!(buffer.contents(), "out\n3\n");
+
+    let buffer = Buffer::default();
+    let mut vm = VirtualMachine::new();
+    vm.set_output(Box::new(buffer.clone()));
+    vm.interpret(&Compiler::new().compile(&statements).unwrap())
+        .unwrap();
+    assert_eq!(buffer.contents(), "out\n3\n");
+
+    let buffer = Buffer::default();
+    let mut interpreter = IrInterpreter::new();
+    interpreter.set_output(Box::new(buffer.clone()));
+    interpreter
+        .interpret(&Lowerer::new().lower(&statements).unwrap())
+        .unwrap();
+    assert_eq!(buffer.contents(), "out\n3\n");
+}
@@ -0,0 +1,135 @@
+use std::{fs, path::Path};
+
+use rlox::error::ErrorType;
+use rlox::formatter::Formatter;
+use rlox::limits::Limits;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::serializer::{Deserializer, Serializer};
+
+fn limits(max_depth: usize) -> Limits {
+    Limits {
+        max_depth: Some(max_depth),
+        ..Limits::default()
+    }
+}
+
+fn compile(source: &str) -> Vec<u8> {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let statements = Parser::with_limits(tokens, limits(1000)).parse().unwrap();
+    Serializer::new().serialize(&statements).unwrap()
+}
+
+// FNV-1a, as the file trailer stores it.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[test]
+fn decoding_nests_exactly_as_deep_as_parsing() {
+    let shapes: [fn(usize) -> String; 9] = [
+        |n| format!("print {}1{};", "(".repeat(n), ")".repeat(n)),
+        |n| format!("print {}1;", "- ".repeat(n)),
+        |n| format!("print {}1{};", "1 + (".repeat(n), ")".repeat(n)),
+        |n| format!("print {}1;", "1 * 2 ** ".repeat(n)),
+        |n| format!("print {}nil;", "nil ?? ".repeat(n)),
+        |n| format!("print {}1{};", "f(".repeat(n), ")".repeat(n)),
+        |n| format!("print f{}.x{};", "(1)".repeat(n), ".y".repeat(n)),
+        |n| format!("print {}3;", "1 ? 2 : ".repeat(n)),
+        |n| format!("{}print 1;{}", "if (true) {".repeat(n), "}".repeat(n)),
+    ];
+    let max_depth = 24;
+
+    for shape in shapes {
+        for n in 1..40 {
+            let source = shape(n);
+            let tokens = Scanner::new(&source).scan_tokens().unwrap();
+            let parsed = Parser::with_limits(tokens, limits(max_depth)).parse();
+            let decoded =
+                Deserializer::with_limits(&compile(&source), limits(max_depth)).deserialize();
+            assert_eq!(parsed.is_ok(), decoded.is_ok(), "{}", source);
+        }
+    }
+}
+
+#[test]
+fn crafted_nesting_is_a_format_error() {
+    let once = compile("print - 1;");
+    let twice = compile("print - - 1;");
+    let start = once
+        .iter()
+        .zip(&twice)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let negation = &twice[start..start + twice.len() - once.len()];
+
+    let mut bytes = twice[..start].to_vec();
+    for _ in 0..100_000 {
+        bytes.extend_from_slice(negation);
+    }
+    bytes.extend_from_slice(&twice[start..twice.len() - 8]);
+    bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+
+    let Err(error) = Deserializer::new(&bytes).deserialize() else {
+        panic!("a crafted file should not load");
+    };
+    assert_eq!(error.etype(), ErrorType::FormatError);
+    assert_eq!(
+        error.message(),
+        "Nesting exceeds the maximum depth of 256 in compiled file"
+    );
+}
+
+fn load(bytes: &[u8]) -> Result<String, String> {
+    match Deserializer::new(bytes).deserialize() {
+        Ok(statements) => Ok(Formatter::new().format(&statements).unwrap()),
+        Err(error) => {
+            assert_eq!(error.etype(), ErrorType::FormatError);
+            Err(error.message().to_string())
+        }
+    }
+}
+
+#[test]
+fn examples_round_trip() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("example");
+    for entry in fs::read_dir(directory).unwrap() {
+        let source = fs::read_to_string(entry.unwrap().path()).unwrap();
+        let tokens = Scanner::new(&source).scan_tokens().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let formatted = Formatter::new().format(&statements).unwrap();
+
+        let bytes = Serializer::new().serialize(&statements).unwrap();
+        assert_eq!(load(&bytes), Ok(formatted));
+    }
+}
+
+#[test]
+fn corrupt_files_are_rejected() {
+    let bytes = compile("var greeting = \"hi\"; print greeting + \" there\";");
+    assert!(load(&bytes).is_ok());
+
+    let mut magic = bytes.clone();
+    magic[0] ^= 0xff;
+    assert_eq!(load(&magic), Err("Not a compiled rlox file".to_string()));
+
+    let mut version = bytes.clone();
+    version[4] = version[4].wrapping_add(1);
+    assert!(load(&version)
+        .unwrap_err()
+        .starts_with("Unsupported compiled file version"));
+
+    let checksum_mismatch = Err("Checksum mismatch, the compiled file is corrupted".to_string());
+    for index in 6..bytes.len() {
+        let mut flipped = bytes.clone();
+        flipped[index] ^= 0x01;
+        assert_eq!(load(&flipped), checksum_mismatch, "byte {}", index);
+    }
+    assert_eq!(load(&bytes[..bytes.len() - 1]), checksum_mismatch);
+
+    for length in 0..6 {
+        assert!(load(&bytes[..length]).is_err());
+    }
+}